@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::announcement::Announcement;
+use crate::state::State;
+
+const ACTIVITY_PATH: &str = "ACTIVITY.json";
+
+// Number of digests remembered, which is about a year of weekly ones.
+const MAX_DIGESTS: usize = 52;
+
+#[cfg(feature = "chart")]
+const WIDTH: u32 = 800;
+#[cfg(feature = "chart")]
+const HEIGHT: u32 = 400;
+
+// How many entries each section of the past digests had, oldest first.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Activity {
+    digests: Vec<Digest>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Digest {
+    date: NaiveDate,
+    sections: BTreeMap<String, usize>,
+}
+
+impl Activity {
+    pub fn load(state: &State) -> Activity {
+        match state.read(ACTIVITY_PATH) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+                tracing::error!(error = %e, "Unable to parse the activity, starting over");
+                Activity::default()
+            }),
+            Err(_) => Activity::default(),
+        }
+    }
+
+    // Remember the number of entries of each section of the digest.
+    pub fn add(&mut self, announcement: &Announcement, date: NaiveDate) {
+        let sections = announcement
+            .sections()
+            .into_iter()
+            .filter(|s| !s.entries.is_empty())
+            .map(|s| (s.title.to_string(), s.entries.len()))
+            .collect();
+        self.digests.push(Digest { date, sections });
+
+        let excess = self.digests.len().saturating_sub(MAX_DIGESTS);
+        self.digests.drain(..excess);
+    }
+
+    pub fn save(&self, state: &State) {
+        state.write(ACTIVITY_PATH, &serde_json::to_string(self).unwrap());
+    }
+
+    // A PNG of the entries per section of the last `digests` digests, as stacked bars. The chart is
+    // only a decoration of the digest, so nothing is returned if it can't be drawn, or if there is
+    // no earlier digest to compare with yet.
+    #[cfg(feature = "chart")]
+    pub fn chart(&self, digests: usize) -> Option<Vec<u8>> {
+        let digests = &self.digests[self.digests.len().saturating_sub(digests)..];
+        if digests.len() < 2 {
+            return None;
+        }
+
+        let mut buffer = vec![0; (WIDTH * HEIGHT * 3) as usize];
+        if let Err(e) = draw(digests, &mut buffer) {
+            tracing::warn!(error = %e, "Unable to draw the activity chart");
+            return None;
+        }
+
+        let image = image::RgbImage::from_raw(WIDTH, HEIGHT, buffer)?;
+        let mut png = std::io::Cursor::new(vec![]);
+        match image.write_to(&mut png, image::ImageFormat::Png) {
+            Ok(()) => Some(png.into_inner()),
+            Err(e) => {
+                tracing::warn!(error = %e, "Unable to encode the activity chart");
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "chart"))]
+    pub fn chart(&self, _: usize) -> Option<Vec<u8>> {
+        tracing::warn!("Built without the `chart` feature, not drawing the activity chart");
+        None
+    }
+}
+
+#[cfg(feature = "chart")]
+fn draw(digests: &[Digest], buffer: &mut [u8]) -> Result<(), Box<dyn std::error::Error>> {
+    use plotters::prelude::*;
+
+    // The sections in the order they first appeared, so that their colours stay the same.
+    let mut titles: Vec<&str> = vec![];
+    for title in digests.iter().flat_map(|d| d.sections.keys()) {
+        if !titles.contains(&title.as_str()) {
+            titles.push(title);
+        }
+    }
+    let total = |d: &Digest| d.sections.values().sum::<usize>();
+    let max = digests.iter().map(total).max().unwrap_or(0).max(1);
+
+    let root = BitMapBackend::with_buffer(buffer, (WIDTH, HEIGHT)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(40)
+        .build_cartesian_2d((0..digests.len() - 1).into_segmented(), 0..max + max / 5)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_label_formatter(&|x| match x {
+            SegmentValue::CenterOf(i) => digests
+                .get(*i)
+                .map_or(String::new(), |d| d.date.format("%b %d").to_string()),
+            _ => String::new(),
+        })
+        .y_desc("Entries")
+        .draw()?;
+
+    for (n, title) in titles.iter().enumerate() {
+        let colour = Palette99::pick(n).mix(0.9);
+        let bars = digests.iter().enumerate().map(|(i, d)| {
+            let below = titles[..n]
+                .iter()
+                .filter_map(|t| d.sections.get(*t))
+                .sum::<usize>();
+            let count = d.sections.get(*title).copied().unwrap_or(0);
+            let mut bar = Rectangle::new(
+                [
+                    (SegmentValue::Exact(i), below),
+                    (SegmentValue::Exact(i + 1), below + count),
+                ],
+                colour.filled(),
+            );
+            bar.set_margin(0, 0, 6, 6);
+            bar
+        });
+        chart
+            .draw_series(bars)?
+            .label(*title)
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], colour.filled()));
+    }
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    Ok(())
+}
@@ -0,0 +1,607 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use minijinja::context;
+use minijinja::value::Serde;
+use minijinja::Environment;
+use minijinja::Value;
+use regex::RegexSet;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::AnnouncementConfig;
+use crate::config::ProjectConfig;
+use crate::locale;
+use crate::sources::artifacts::Artifacts;
+use crate::sources::changelog::Release;
+use crate::sources::devblog::Devblog;
+use crate::sources::milestone::Progress;
+use crate::sources::twitch::Stream;
+use crate::sources::Update;
+use crate::state;
+
+// The layout of the message when no template is configured. Templates are rendered with the
+// `sections` of the announcement, each with a `title`, an optional `emoji` and a list of `entries`,
+// the `releases`, each with a `version`, its `notes`, the `launcher` and its `downloads`, its
+// `date` and the number of `days` since the previous version, the
+// `artifacts`, each with a `version`, the `url` of the release and its `downloads`, where each
+// download has a `name` and `url`, the `devblogs`, each with a `url`, `title`, `date`, `image` and
+// `excerpt`, the `streams`, each with a `channel`, `title`, `game`, `url` and `thumbnail`, and the
+// `summary` of the changes, if one was generated. Sections that have more entries than allowed
+// only list the first ones, followed by a note saying how many `more` there are. The `heading` and
+// the titles of the sections are translated for guilds with another language.
+const DEFAULT_TEMPLATE: &str = "\
+# {{ heading }}
+{%- if summary %}
+{{ summary }}
+{%- endif %}
+{%- for section in sections %}
+## {% if section.emoji %}{{ section.emoji }} {% endif %}{{ section.title }}
+{%- for entry in section.entries %}
+- {{ entry }}
+{%- endfor %}
+{%- if section.more %}
+- {{ section.more }}
+{%- endif %}
+{%- endfor %}";
+
+// Everything that is posted in a single run. The changes are markdown lines, with `## ` headers
+// starting each section.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Announcement {
+    pub changes: Vec<String>,
+    pub releases: Vec<Release>,
+    // Announcements are saved when they can't be delivered, so fields that were added later need
+    // defaults.
+    #[serde(default)]
+    pub artifacts: Vec<Artifacts>,
+    pub devblogs: Vec<Devblog>,
+    #[serde(default)]
+    pub streams: Vec<Stream>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub milestones: Vec<Progress>,
+    // Titles of the milestones that are now complete.
+    #[serde(default)]
+    pub completed: Vec<String>,
+    // A PNG of the activity of the past digests. It's too big to keep with the announcements that
+    // are saved, so those are delivered without it.
+    #[serde(skip)]
+    pub chart: Option<Vec<u8>>,
+    #[serde(default = "default_heading")]
+    heading: String,
+    // Code of the language that the announcement was translated into, English if unset.
+    #[serde(default)]
+    language: Option<String>,
+    // Titles to display instead of the titles of the sections, which stay the same in the changes
+    // so that they can still be merged.
+    #[serde(default)]
+    titles: BTreeMap<String, String>,
+    template: String,
+    emoji: BTreeMap<String, String>,
+    order: Vec<String>,
+    #[serde(default)]
+    max_entries: Option<usize>,
+    // The name of the project that the announcement is about. Only projects other than Veloren are
+    // also named by `project`, which heads their announcements. The channels are the Discord
+    // channels to post to instead of the configured ones.
+    #[serde(default = "default_name")]
+    pub name: String,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub channels: Vec<u64>,
+    // The hash of the announcement before it was translated, so that every translation of it has
+    // the same hash.
+    #[serde(default)]
+    original: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Section<'a> {
+    pub title: &'a str,
+    pub emoji: Option<&'a str>,
+    pub entries: Vec<&'a str>,
+    // The note about the entries that were left out, if any were.
+    pub more: Option<String>,
+}
+
+impl Announcement {
+    // Gather the updates of all sources into one announcement, or nothing if there are no updates.
+    // Filtered sections and ignored entries are left out here, after diffing, so that they are still
+    // saved as seen.
+    pub fn new(
+        updates: Vec<Update>,
+        config: &AnnouncementConfig,
+        project_config: &ProjectConfig,
+    ) -> Option<Announcement> {
+        let name = project_config.name();
+        let mut sections = vec![];
+        let mut releases = vec![];
+        let mut artifacts = vec![];
+        let mut devblogs = vec![];
+        let mut streams = vec![];
+        let mut milestones = vec![];
+        let mut completed = vec![];
+
+        // The patterns have already been checked when loading the config.
+        let ignore = RegexSet::new(&config.ignore).expect("Unable to parse the ignore patterns.");
+
+        for update in updates {
+            match update {
+                Update::Section { title, entries } => {
+                    let entries = entries
+                        .into_iter()
+                        .filter(|e| !ignore.is_match(e.strip_prefix("- ").unwrap_or(e)))
+                        .collect::<Vec<_>>();
+
+                    if entries.is_empty() || !config.announces(&title) {
+                        continue;
+                    }
+
+                    add(&mut sections, &title, entries);
+                }
+                Update::Release(r) => releases.push(r),
+                Update::Artifacts(a) => artifacts.push(a),
+                Update::Devblog(d) => devblogs.push(d),
+                Update::Stream(s) => streams.push(s),
+                Update::Milestone(m) => milestones.push(m),
+                Update::Completed(m) => completed.push(m),
+                Update::Retracted(_) => {}
+            }
+        }
+
+        // The entries made up from the updates are in English here, and translated along with the
+        // titles of the sections.
+        //
+        // The download links are too long for the message, so only the platforms are listed, with
+        // the links going where the platform can show them, e.g. the embeds on Discord.
+        if !artifacts.is_empty() {
+            let entries = artifacts.iter().map(|a| download_entry(None, name, a));
+            add(&mut sections, "Downloads", entries.collect());
+        }
+
+        if !streams.is_empty() {
+            let entries = streams.iter().map(|s| live_entry(None, s));
+            add(&mut sections, "Live", entries.collect());
+        }
+
+        if !milestones.is_empty() {
+            let entries = milestones.iter().map(|m| milestone_entry(None, m));
+            add(&mut sections, "Milestones", entries.collect());
+        }
+
+        let mut changes = flatten(sections, &config.order);
+
+        // Releases go first, since they are the bigger news. Their notes are long and have mostly
+        // been announced already, so only the versions are listed.
+        if !releases.is_empty() {
+            let versions = releases.iter().map(|r| release_entry(None, name, r));
+            changes.splice(
+                0..0,
+                std::iter::once("## Released".to_string()).chain(versions),
+            );
+        }
+
+        // The embeds are displayed below the message, so the devblog section header is kept as
+        // the last line of the message.
+        if !devblogs.is_empty() {
+            changes.push("## Blog post(s)".to_string());
+        }
+
+        if changes.is_empty() {
+            return None;
+        }
+
+        // Projects other than Veloren are headed with their name.
+        let project = (!project_config.is_veloren()).then(|| name.to_string());
+        let heading = match &project {
+            Some(name) => locale::text(None, "project-title", &[("project", name)]),
+            None => default_heading(),
+        };
+
+        let template = config
+            .loaded_template
+            .clone()
+            .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string());
+
+        Some(Announcement {
+            changes,
+            releases,
+            artifacts,
+            devblogs,
+            streams,
+            summary: None,
+            milestones,
+            completed,
+            chart: None,
+            heading,
+            language: None,
+            titles: BTreeMap::new(),
+            template,
+            emoji: config.emoji.clone(),
+            order: config.order.clone(),
+            max_entries: config.max_entries,
+            name: name.to_string(),
+            project,
+            channels: project_config.channels.clone(),
+            original: None,
+        })
+    }
+
+    // Add the changes of an earlier announcement, so that both are announced in one message. The
+    // entries of sections with the same title are combined, with the earlier ones first.
+    pub fn merge(&self, earlier: &[String]) -> Announcement {
+        let mut sections = vec![];
+        for changes in [earlier, &self.changes] {
+            let mut title = None;
+            for line in changes {
+                match line.strip_prefix("## ") {
+                    Some(t) => {
+                        add(&mut sections, t, vec![]);
+                        title = Some(t);
+                    }
+                    None => {
+                        if let Some(t) = title {
+                            add(&mut sections, t, vec![line.clone()]);
+                        }
+                    }
+                }
+            }
+        }
+
+        Announcement {
+            changes: flatten(sections, &self.order),
+            ..self.clone()
+        }
+    }
+
+    // The announcement as markdown, rendered with the template. The devblogs are only included if
+    // the template does so, since each platform displays them in its own way.
+    pub fn message(&self) -> String {
+        let ctx = context! {
+            sections => Value::from(Serde(&self.collapsed())),
+            releases => Value::from(Serde(&self.releases)),
+            artifacts => Value::from(Serde(&self.artifacts)),
+            devblogs => Value::from(Serde(&self.devblogs)),
+            streams => Value::from(Serde(&self.streams)),
+            summary => self.summary.as_deref(),
+            heading => &self.heading,
+        };
+        Environment::new()
+            .render_str(&self.template, ctx.clone())
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "Unable to render the template, using the default");
+                Environment::new()
+                    .render_str(DEFAULT_TEMPLATE, ctx)
+                    .expect("Unable to render the default template.")
+            })
+    }
+
+    // The entries of every section, without their bullets.
+    pub fn entries(&self) -> Vec<&str> {
+        self.changes
+            .iter()
+            .filter(|l| !l.starts_with("## "))
+            .map(|l| l.strip_prefix("- ").unwrap_or(l))
+            .collect()
+    }
+
+    // The announcement without the given entries, which also leaves out the sections that have no
+    // entries left. Entries that were announced with the lines that continue them still match.
+    pub fn without(&self, entries: &[String]) -> Announcement {
+        let removed = |line: &str| {
+            entries.iter().any(|e| {
+                line.strip_prefix(e.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\n']))
+            })
+        };
+
+        let mut changes: Vec<String> = vec![];
+        for line in self.changes.iter().filter(|l| !removed(l)) {
+            if line.starts_with("## ") && changes.last().is_some_and(|l| l.starts_with("## ")) {
+                changes.pop();
+            }
+            changes.push(line.clone());
+        }
+        if changes.last().is_some_and(|l| l.starts_with("## ")) {
+            changes.pop();
+        }
+
+        Announcement {
+            changes,
+            ..self.clone()
+        }
+    }
+
+    // The announcement marked as a correction of the one that was posted before.
+    pub fn corrected(&self) -> Announcement {
+        let note = format!(" ({})", locale::text(self.language(), "corrected", &[]));
+        let heading = match self.heading.ends_with(&note) {
+            true => self.heading.clone(),
+            false => self.heading.clone() + &note,
+        };
+        Announcement {
+            heading,
+            ..self.clone()
+        }
+    }
+
+    // Whether nothing is left to announce.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+            && self.releases.is_empty()
+            && self.artifacts.is_empty()
+            && self.devblogs.is_empty()
+            && self.streams.is_empty()
+    }
+
+    // A hash of what is announced, which recognizes an announcement that has already been posted.
+    pub fn hash(&self) -> String {
+        if let Some(hash) = &self.original {
+            return hash.clone();
+        }
+
+        let announced = (
+            &self.changes,
+            &self.releases,
+            &self.artifacts,
+            &self.devblogs,
+            &self.streams,
+            &self.project,
+        );
+        let json = serde_json::to_string(&announced).expect("Unable to hash the announcement.");
+        format!("{:016x}", state::checksum(&json))
+    }
+
+    pub fn heading(&self) -> &str {
+        &self.heading
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    // The announcement with every entry changed by `f`, e.g. to escape it for a platform.
+    pub fn map_entries(&self, f: impl Fn(&str) -> String) -> Announcement {
+        let entries = self.entries().into_iter().map(f).collect();
+        Announcement {
+            changes: self.with_entries(entries),
+            ..self.clone()
+        }
+    }
+
+    // The changes with the entries replaced by the given ones, which are in the order of `entries`.
+    fn with_entries(&self, entries: Vec<String>) -> Vec<String> {
+        let mut changes = self.changes.clone();
+        let lines = changes.iter_mut().filter(|l| !l.starts_with("## "));
+        for (line, entry) in lines.zip(entries) {
+            *line = "- ".to_string() + &entry;
+        }
+        changes
+    }
+
+    // The announcement in another language, with the heading and the titles of the sections
+    // replaced by the given ones, and the entries by their translations if there are any, which
+    // are in the order of `entries`.
+    pub fn translated(
+        &self,
+        language: &str,
+        heading: String,
+        titles: BTreeMap<String, String>,
+        entries: Option<Vec<String>>,
+    ) -> Announcement {
+        let mut changes = match entries {
+            Some(entries) => self.with_entries(entries),
+            None => self.changes.clone(),
+        };
+
+        // The entries made up from the updates have translations of their own, which are better than
+        // machine-translated ones.
+        let english = self.generated(None);
+        let localized = english
+            .into_iter()
+            .zip(self.generated(Some(language)))
+            .collect::<HashMap<_, _>>();
+        for (line, original) in changes.iter_mut().zip(&self.changes) {
+            if let Some(s) = localized.get(original) {
+                *line = s.clone();
+            }
+        }
+
+        Announcement {
+            changes,
+            heading,
+            language: Some(language.to_string()),
+            titles,
+            original: Some(self.hash()),
+            ..self.clone()
+        }
+    }
+
+    // The entries that are made up from the updates instead of taken from a source, in the given
+    // language.
+    fn generated(&self, language: Option<&str>) -> Vec<String> {
+        let name = &self.name;
+        let releases = self
+            .releases
+            .iter()
+            .map(|r| release_entry(language, name, r));
+        let artifacts = self
+            .artifacts
+            .iter()
+            .map(|a| download_entry(language, name, a));
+        let streams = self.streams.iter().map(|s| live_entry(language, s));
+        let milestones = self.milestones.iter().map(|m| milestone_entry(language, m));
+        releases
+            .chain(artifacts)
+            .chain(streams)
+            .chain(milestones)
+            .collect()
+    }
+
+    // Like `sections`, but with long sections cut off after the allowed number of entries. The full
+    // list can still be looked up with `/changelog`.
+    fn collapsed(&self) -> Vec<Section<'_>> {
+        let mut sections = self.sections();
+        let Some(max) = self.max_entries else {
+            return sections;
+        };
+
+        for section in &mut sections {
+            if section.entries.len() > max {
+                let more = section.entries.len() - max;
+                section.entries.truncate(max);
+                section.more = Some(locale::text(
+                    self.language(),
+                    "more",
+                    &[("count", &more.to_string())],
+                ));
+            }
+        }
+        sections
+    }
+
+    // The changes grouped by section, without the bullets of the entries.
+    pub fn sections(&self) -> Vec<Section<'_>> {
+        let mut sections: Vec<Section> = vec![];
+        for line in &self.changes {
+            match line.strip_prefix("## ") {
+                Some(title) => sections.push(Section {
+                    title: self.titles.get(title).map_or(title, |s| s.as_str()),
+                    emoji: self.emoji.get(title).map(|s| s.as_str()),
+                    entries: vec![],
+                    more: None,
+                }),
+                None => {
+                    if let Some(section) = sections.last_mut() {
+                        section
+                            .entries
+                            .push(line.strip_prefix("- ").unwrap_or(line));
+                    }
+                }
+            }
+        }
+
+        sections
+    }
+}
+
+fn default_heading() -> String {
+    locale::title(None)
+}
+
+fn default_name() -> String {
+    "Veloren".to_string()
+}
+
+fn release_entry(language: Option<&str>, name: &str, release: &Release) -> String {
+    let version = match &release.compare {
+        Some(url) => format!("[{} {}]({})", name, release.version, url),
+        None => format!("{} {}", name, release.version),
+    };
+    match release.days {
+        Some(days) => {
+            let days = days.to_string();
+            let args = [("version", version.as_str()), ("days", &days)];
+            format!("- {}", locale::text(language, "released-after", &args))
+        }
+        None => format!("- {}", version),
+    }
+}
+
+fn download_entry(language: Option<&str>, name: &str, artifacts: &Artifacts) -> String {
+    let platforms = artifacts.downloads.iter().map(|d| d.name.as_str());
+    let platforms = platforms.collect::<Vec<_>>().join(", ");
+    let args = [
+        ("project", name),
+        ("version", &artifacts.version),
+        ("platforms", &platforms),
+    ];
+    format!("- {}", locale::text(language, "downloads-for", &args))
+}
+
+fn live_entry(language: Option<&str>, stream: &Stream) -> String {
+    let live = locale::text(language, "live", &[("channel", &stream.channel)]);
+    format!("- {}: {}", live, stream.title)
+}
+
+fn milestone_entry(language: Option<&str>, milestone: &Progress) -> String {
+    let key = match milestone.threshold {
+        Some(100) => "milestone-complete",
+        Some(_) => "milestone-progress",
+        None => "milestone-closed",
+    };
+    let percent = milestone.threshold.unwrap_or(0).to_string();
+    let args = [
+        ("milestone", milestone.title.as_str()),
+        ("percent", &percent),
+    ];
+    format!("- {}", locale::text(language, key, &args))
+}
+
+// Add the entries to the section with the given title, so that upstream sections which are split
+// under several headers are announced as one.
+fn add(sections: &mut Vec<(String, Vec<String>)>, title: &str, entries: Vec<String>) {
+    match sections.iter_mut().find(|(t, _)| t == title) {
+        Some((_, e)) => e.extend(entries),
+        None => sections.push((title.to_string(), entries)),
+    }
+}
+
+// Order the sections and turn them into lines. The sort is stable, so unlisted sections keep the
+// order they were found in.
+fn flatten(mut sections: Vec<(String, Vec<String>)>, order: &[String]) -> Vec<String> {
+    // Released versions with their downloads, and live streams stay first.
+    let rank = |title: &str| match title {
+        "Released" => 0,
+        "Downloads" => 1,
+        "Live" => 2,
+        t => 3 + order.iter().position(|o| o == t).unwrap_or(order.len()),
+    };
+    sections.sort_by_key(|(t, _)| rank(t));
+
+    let mut changes = vec![];
+    for (title, entries) in sections {
+        changes.push("## ".to_string() + &title);
+        changes.extend(entries);
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn milestone(threshold: Option<u8>) -> Update {
+        Update::Milestone(Progress {
+            title: "0.16".to_string(),
+            threshold,
+        })
+    }
+
+    #[test]
+    fn made_up_entries_are_translated() {
+        let updates = vec![milestone(Some(50)), milestone(None)];
+        let config = AnnouncementConfig::default();
+        let announcement = Announcement::new(updates, &config, &ProjectConfig::default()).unwrap();
+        assert_eq!(
+            announcement.changes,
+            vec![
+                "## Milestones",
+                "- The 0.16 milestone is now 50% complete",
+                "- The 0.16 milestone has been closed",
+            ]
+        );
+
+        let german = announcement.translated("de", String::new(), BTreeMap::new(), None);
+        assert_eq!(
+            german.changes[1..],
+            [
+                "- Der Meilenstein 0.16 ist jetzt zu 50 % abgeschlossen",
+                "- Der Meilenstein 0.16 wurde geschlossen",
+            ]
+        );
+    }
+}
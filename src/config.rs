@@ -0,0 +1,746 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use chrono::NaiveTime;
+use chrono::Weekday;
+use chrono_tz::Tz;
+use cron::Schedule;
+use minijinja::Environment;
+use regex::RegexSet;
+use serde::Deserialize;
+
+use crate::sources::changelog_url;
+
+pub const CONFIG_PATH: &str = "config.toml";
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub matrix: Option<MatrixConfig>,
+    pub telegram: Option<TelegramConfig>,
+    pub mastodon: Option<MastodonConfig>,
+    pub slack: Option<SlackConfig>,
+    pub irc: Option<IrcConfig>,
+    pub site: Option<SiteConfig>,
+    pub retry: RetryConfig,
+    pub http: HttpConfig,
+    pub log: LogConfig,
+    pub daemon: DaemonConfig,
+    pub announcement: AnnouncementConfig,
+    pub discord: DiscordConfig,
+    pub digest: Option<DigestConfig>,
+    pub batch: Option<BatchConfig>,
+    pub milestones: Option<MilestonesConfig>,
+    pub downloads: DownloadsConfig,
+    pub reddit: Option<RedditConfig>,
+    pub youtube: Option<YoutubeConfig>,
+    pub github: Option<GithubConfig>,
+    pub twitch: Option<TwitchConfig>,
+    pub wiki: Option<WikiConfig>,
+    pub summary: Option<SummaryConfig>,
+    pub projects: Vec<ProjectConfig>,
+}
+
+// A project whose changelog and blog are watched, with its own announcements. Without any
+// projects, only Veloren is watched, as if it was configured with its preset.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    // Fills in the name, changelog and blog of a known project. Only the Veloren preset polls the
+    // other sources configured at the top level, and keeps its state in the state directory itself
+    // instead of a sub-directory named after the project.
+    pub preset: Option<Preset>,
+    pub name: Option<String>,
+    // The raw `CHANGELOG.md`, in the Keep a Changelog format.
+    pub changelog_url: Option<String>,
+    // The RSS or Atom feed of the blog.
+    pub blog_feed_url: Option<String>,
+    // The path of the project on GitLab, e.g. `veloren/veloren`, whose releases are announced once
+    // they have downloads.
+    pub gitlab_project: Option<String>,
+    // IDs of the Discord channels to post to, instead of those of `[discord]`.
+    pub channels: Vec<u64>,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    Veloren,
+}
+
+impl ProjectConfig {
+    fn veloren() -> ProjectConfig {
+        ProjectConfig {
+            preset: Some(Preset::Veloren),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_veloren(&self) -> bool {
+        self.preset == Some(Preset::Veloren)
+    }
+
+    pub fn name(&self) -> &str {
+        match &self.name {
+            Some(name) => name,
+            None => "Veloren",
+        }
+    }
+
+    // The path of the project on GitLab, which is Veloren's own for the preset unless configured
+    // otherwise.
+    pub fn gitlab_project(&self) -> Option<&str> {
+        self.gitlab_project
+            .as_deref()
+            .or_else(|| self.is_veloren().then_some("veloren/veloren"))
+    }
+
+    // The directory of the state files of the project.
+    pub fn state_dir(&self, root: &Path) -> PathBuf {
+        if self.is_veloren() {
+            return root.to_path_buf();
+        }
+
+        let name = self
+            .name()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect::<String>();
+        root.join(name)
+    }
+}
+
+// An OpenAI-compatible API that is asked for a short summary of each announcement.
+#[derive(Clone, Deserialize)]
+pub struct SummaryConfig {
+    // The base URL of the API, e.g. `https://api.openai.com/v1`.
+    pub url: String,
+    pub model: String,
+    pub api_key: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct WikiConfig {
+    // The `api.php` endpoint of the wiki.
+    pub api_url: String,
+    // Edits are only announced if they change the size of the page by at least this much, while new
+    // pages are always announced.
+    pub min_bytes: u64,
+}
+
+impl Default for WikiConfig {
+    fn default() -> WikiConfig {
+        WikiConfig {
+            api_url: "https://wiki.veloren.net/api.php".to_string(),
+            min_bytes: 1000,
+        }
+    }
+}
+
+// Credentials of an application registered with Twitch, which are only used for public data.
+#[derive(Clone, Deserialize)]
+pub struct TwitchConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    // Logins of the channels to announce when they go live.
+    #[serde(default = "default_twitch_channels")]
+    pub channels: Vec<String>,
+}
+
+fn default_twitch_channels() -> Vec<String> {
+    vec!["veloren".to_string()]
+}
+
+#[derive(Clone, Deserialize)]
+pub struct YoutubeConfig {
+    // The ID of the channel, as in `https://www.youtube.com/channel/<ID>`.
+    pub channel_id: String,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct GithubConfig {
+    // Repositories whose releases are announced, as `<owner>/<name>`.
+    pub repositories: Vec<String>,
+    // Whether to announce the releases marked as pre-releases too.
+    #[serde(default)]
+    pub prereleases: bool,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct RedditConfig {
+    pub subreddit: String,
+    // Flairs of the posts to include, matched case-insensitively.
+    pub flairs: Vec<String>,
+}
+
+impl Default for RedditConfig {
+    fn default() -> RedditConfig {
+        RedditConfig {
+            subreddit: "Veloren".to_string(),
+            flairs: vec!["Official".to_string(), "Dev".to_string()],
+        }
+    }
+}
+
+// Where tagged releases can be downloaded, which is linked in their announcements.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct DownloadsConfig {
+    pub launcher: Option<String>,
+    // The download of each platform, by platform name, with `{version}` replaced by the version.
+    pub platforms: BTreeMap<String, String>,
+}
+
+impl Default for DownloadsConfig {
+    fn default() -> DownloadsConfig {
+        let platforms = [
+            (
+                "Windows",
+                "https://download.veloren.net/latest/windows/x86_64/weekly",
+            ),
+            (
+                "Linux",
+                "https://download.veloren.net/latest/linux/x86_64/weekly",
+            ),
+            (
+                "macOS",
+                "https://download.veloren.net/latest/macos/aarch64/weekly",
+            ),
+        ];
+
+        DownloadsConfig {
+            launcher: Some("https://veloren.net/download/".to_string()),
+            platforms: platforms
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct MilestonesConfig {
+    // Percentages of closed issues at which a milestone is announced.
+    pub thresholds: Vec<u8>,
+}
+
+impl Default for MilestonesConfig {
+    fn default() -> MilestonesConfig {
+        MilestonesConfig {
+            thresholds: vec![50, 75, 90, 100],
+        }
+    }
+}
+
+// Hold back the updates until enough of them have been found, or the oldest has waited long enough.
+#[derive(Clone, Deserialize)]
+pub struct BatchConfig {
+    pub min_entries: usize,
+    pub max_age_days: u64,
+}
+
+// Collect the updates during the week and announce them all at once.
+#[derive(Clone, Deserialize)]
+pub struct DigestConfig {
+    // E.g. `friday` or `fri`.
+    pub weekday: String,
+    // In UTC, as `HH:MM`.
+    pub time: String,
+    // Number of past weeks to chart the entries per section of, attached to the digest on Discord.
+    // No chart is attached if unset.
+    pub chart_weeks: Option<usize>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DiscordConfig {
+    // Whether to pin every announcement, unpinning the previous one.
+    pub pin: bool,
+    // Add new entries to the latest announcement instead of posting a new one for this long after
+    // it was posted. Zero disables editing.
+    pub edit_window_hours: u64,
+    // IDs of the only guilds to post to, all guilds that the bot is in if unset.
+    pub allow_guilds: Option<Vec<u64>>,
+    // IDs of guilds to never post to.
+    pub deny_guilds: Vec<u64>,
+    // IDs of the channels to post to, instead of every channel named "veloren-updates".
+    pub channels: Vec<u64>,
+    // Settings of individual guilds, by guild ID.
+    pub guilds: BTreeMap<String, GuildConfig>,
+    // Changes to the built-in translations of the announcements, by language code.
+    pub languages: BTreeMap<String, LanguageConfig>,
+    // The service that translates the entries for languages that have `translate` enabled.
+    pub translation: Option<TranslationConfig>,
+    // Buttons linking to pages about the game below every announcement, instead of the default
+    // ones.
+    pub links: Option<Vec<LinkConfig>>,
+    // Send the requests to Discord with the client of `[http]`, through its proxy. The gateway
+    // connection, which the bot receives the commands over, is never proxied.
+    pub proxy: bool,
+    // Number of guilds posted to at once, 8 if unset.
+    pub concurrency: Option<usize>,
+    // The changelog that the slash commands are about, which is the one of the first project.
+    #[serde(skip)]
+    pub changelog_url: Option<String>,
+    // How to correct the announcements with entries that were removed upstream since, which are
+    // left as they are if unset.
+    pub corrections: Option<Corrections>,
+    // Scheduled events to create for new releases and milestones that are complete, which aren't
+    // created if unset.
+    pub events: Option<EventsConfig>,
+    // A private channel that every announcement is posted to for review first, which is only
+    // announced once it's approved there.
+    pub staging: Option<StagingConfig>,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Corrections {
+    // Edit the message of the announcement, or repost it if the correction doesn't fit.
+    Edit,
+    // Delete the messages of the announcement and post it anew.
+    Repost,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct StagingConfig {
+    pub channel: u64,
+    // IDs of the users who may approve or reject announcements, and of the roles whose members may.
+    // Without either, whoever can manage the guild of the channel may.
+    #[serde(default)]
+    pub reviewers: Vec<u64>,
+    #[serde(default)]
+    pub roles: Vec<u64>,
+    // How long to wait for a review, after which the announcement is dropped, to be staged again by
+    // a later check.
+    #[serde(default = "default_review_hours")]
+    pub timeout_hours: u64,
+}
+
+fn default_review_hours() -> u64 {
+    24
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct EventsConfig {
+    // IDs of the guilds to create the events in.
+    pub guilds: Vec<u64>,
+    // How long after the announcement the event starts, and how long it lasts.
+    pub starts_after_minutes: u64,
+    pub duration_minutes: u64,
+    // Where the event takes place, as shown on it.
+    pub location: String,
+}
+
+impl Default for EventsConfig {
+    fn default() -> EventsConfig {
+        EventsConfig {
+            guilds: vec![],
+            starts_after_minutes: 60,
+            duration_minutes: 120,
+            location: "In game".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct LinkConfig {
+    pub label: String,
+    pub url: String,
+}
+
+impl Config {
+    // The watched projects, which is only Veloren unless others are configured.
+    pub fn projects(&self) -> Vec<ProjectConfig> {
+        match self.projects.is_empty() {
+            true => vec![ProjectConfig::veloren()],
+            false => self.projects.clone(),
+        }
+    }
+
+    // The project of the given name, or the first one if no name is given.
+    pub fn project(&self, name: Option<&str>) -> Option<ProjectConfig> {
+        let mut projects = self.projects().into_iter();
+        match name {
+            Some(name) => projects.find(|p| p.name().eq_ignore_ascii_case(name)),
+            None => projects.next(),
+        }
+    }
+}
+
+impl DiscordConfig {
+    pub fn allows(&self, guild: u64) -> bool {
+        let allowed = match &self.allow_guilds {
+            Some(allow) => allow.contains(&guild),
+            None => true,
+        };
+
+        allowed && !self.deny_guilds.contains(&guild)
+    }
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GuildConfig {
+    // Roles to mention when a new entry contains a keyword, as keyword to role ID. Keywords are
+    // matched case-insensitively.
+    pub mentions: BTreeMap<String, u64>,
+    // IDs of the channels to post to in the guild, replacing the global channels.
+    pub channels: Vec<u64>,
+    // Code of the language of the announcements in the guild, unless another one is chosen with
+    // `/setup language`.
+    pub language: Option<String>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LanguageConfig {
+    // Replaces the translation of "Veloren News!".
+    pub title: Option<String>,
+    // Names of the sections, by their original title, e.g. "Added" or "Blog post(s)", in addition
+    // to the translated ones.
+    pub sections: BTreeMap<String, String>,
+    // Whether to have the entries machine-translated into the language.
+    pub translate: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TranslationConfig {
+    pub backend: TranslationBackend,
+    // The base URL of the service, e.g. `https://api-free.deepl.com` or
+    // `https://libretranslate.com`.
+    pub url: String,
+    pub api_key: Option<String>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranslationBackend {
+    Deepl,
+    Libretranslate,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct AnnouncementConfig {
+    // Path to a minijinja template for the message, instead of the built-in layout.
+    pub template: Option<String>,
+    // The template read from `template` when the config was loaded.
+    #[serde(skip)]
+    pub loaded_template: Option<String>,
+    // Decoration for the header of each section, by section title.
+    pub emoji: BTreeMap<String, String>,
+    // Titles of the sections to announce, all of them if unset.
+    pub include: Option<Vec<String>>,
+    // Titles of the sections to never announce.
+    pub exclude: Vec<String>,
+    // Regexes matching entries to never announce.
+    pub ignore: Vec<String>,
+    // Order of the sections by title. Sections that aren't listed follow in the order they were
+    // found.
+    pub order: Vec<String>,
+    // Number of entries to show in each section of the message, all of them if unset.
+    pub max_entries: Option<usize>,
+}
+
+impl AnnouncementConfig {
+    pub fn announces(&self, section: &str) -> bool {
+        let included = match &self.include {
+            Some(include) => include.iter().any(|s| s == section),
+            None => true,
+        };
+
+        included && !self.exclude.iter().any(|s| s == section)
+    }
+}
+
+impl Default for AnnouncementConfig {
+    fn default() -> AnnouncementConfig {
+        let emoji = [
+            ("Added", "✨"),
+            ("Changed", "🔧"),
+            ("Fixed", "🐛"),
+            ("Removed", "🗑"),
+            ("New contributors", "🎉"),
+        ];
+
+        AnnouncementConfig {
+            template: None,
+            loaded_template: None,
+            emoji: emoji
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            include: None,
+            exclude: vec![],
+            ignore: vec![],
+            order: ["Added", "Changed", "Fixed", "Removed"]
+                .into_iter()
+                .map(|s| s.to_string())
+                .collect(),
+            max_entries: None,
+        }
+    }
+}
+
+// Only used when running as a daemon with the `daemon` subcommand.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct DaemonConfig {
+    // How long to wait between checks for new changes.
+    pub interval_secs: u64,
+    // A cron expression for when to check instead of the interval, e.g. `0 18 * * Wed` for every
+    // Wednesday at 18:00. Seconds and years may be given as extra fields.
+    pub schedule: Option<String>,
+    // The timezone of the schedule, e.g. `Europe/Berlin`.
+    pub timezone: String,
+    // Address to serve Prometheus metrics at `/metrics` and health at `/healthz` on, e.g.
+    // `127.0.0.1:9184`.
+    pub http_addr: Option<String>,
+    // Bearer token that `POST /check` on the HTTP address needs to check for changes right away.
+    // The endpoint is disabled without one.
+    pub check_token: Option<String>,
+    // Secret token of a GitLab push webhook at `/webhook/gitlab` on the HTTP address, which
+    // checks for changes right away when a watched changelog is pushed to. Disabled without one.
+    pub gitlab_webhook_secret: Option<String>,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> DaemonConfig {
+        DaemonConfig {
+            interval_secs: 3600,
+            schedule: None,
+            timezone: "UTC".to_string(),
+            http_addr: None,
+            check_token: None,
+            gitlab_webhook_secret: None,
+        }
+    }
+}
+
+impl DaemonConfig {
+    // The schedule in its timezone, if one is configured.
+    pub fn schedule(&self) -> Result<Option<(Schedule, Tz)>, String> {
+        let Some(expression) = &self.schedule else {
+            return Ok(None);
+        };
+
+        // The usual cron expressions have no field for seconds, which the parser expects first.
+        let expression = match expression.split_whitespace().count() {
+            5 => format!("0 {}", expression),
+            _ => expression.clone(),
+        };
+        let schedule = expression
+            .parse::<Schedule>()
+            .map_err(|e| format!("Unable to parse the schedule: {}", e))?;
+        let timezone = self
+            .timezone
+            .parse::<Tz>()
+            .map_err(|e| format!("Unable to parse the timezone: {}", e))?;
+
+        Ok(Some((schedule, timezone)))
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    // A filter in the syntax of `RUST_LOG`, e.g. `info` or `veloren_changelog=debug`.
+    pub level: String,
+    pub json: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> LogConfig {
+        LogConfig {
+            level: "info".to_string(),
+            json: false,
+        }
+    }
+}
+
+// The client that every download and webhook request is sent with. Timeouts are per request, so a
+// retried request can take longer.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub connect_timeout_secs: u64,
+    // The whole request, until the body has been read.
+    pub timeout_secs: u64,
+    pub user_agent: String,
+    // Send every request through a proxy, e.g. `http://proxy:3128` or `socks5h://proxy:1080`.
+    // Without it, the `HTTPS_PROXY` environment variable and the like are used.
+    pub proxy: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> HttpConfig {
+        HttpConfig {
+            connect_timeout_secs: 10,
+            timeout_secs: 30,
+            user_agent: concat!("veloren-changelog/", env!("CARGO_PKG_VERSION")).to_string(),
+            proxy: None,
+        }
+    }
+}
+
+// How often failed requests are retried, for transient errors only.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    // Total number of attempts, including the first one.
+    pub attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> RetryConfig {
+        RetryConfig {
+            attempts: 4,
+            base_delay_ms: 1000,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct MatrixConfig {
+    pub homeserver: String,
+    pub access_token: String,
+    pub rooms: Vec<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    // Chat IDs or `@channelusername`s.
+    pub chats: Vec<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct MastodonConfig {
+    // Base URL of the instance, e.g. `https://mastodon.social`.
+    pub instance: String,
+    pub access_token: String,
+    #[serde(default = "default_changelog_link")]
+    pub link: String,
+    #[serde(default = "default_mastodon_visibility")]
+    pub visibility: String,
+    // Whether to post the full announcement as replies to the summary.
+    #[serde(default = "default_true")]
+    pub thread: bool,
+    #[serde(default = "default_mastodon_max_length")]
+    pub max_length: usize,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct SlackConfig {
+    // Incoming webhook URLs, one per channel.
+    pub webhooks: Vec<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct SiteConfig {
+    // Directory to write the pages to, which is created if needed.
+    pub dir: String,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct IrcConfig {
+    pub server: String,
+    #[serde(default = "default_irc_port")]
+    pub port: u16,
+    #[serde(default = "default_true")]
+    pub tls: bool,
+    pub nickname: String,
+    pub password: Option<String>,
+    pub channels: Vec<String>,
+    // Lines after which the announcement is cut off with a link to the full changelog.
+    #[serde(default = "default_irc_max_lines")]
+    pub max_lines: usize,
+    #[serde(default = "default_changelog_link")]
+    pub link: String,
+}
+
+fn default_irc_port() -> u16 {
+    6697
+}
+
+fn default_irc_max_lines() -> usize {
+    15
+}
+
+fn default_changelog_link() -> String {
+    "https://gitlab.com/veloren/veloren/-/blob/weekly/CHANGELOG.md".to_string()
+}
+
+fn default_mastodon_visibility() -> String {
+    "public".to_string()
+}
+
+fn default_mastodon_max_length() -> usize {
+    500
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Config {
+    // Load the config file, and the template it refers to. A missing config file is the same as an
+    // empty one.
+    pub fn try_load(path: &str) -> Result<Config, String> {
+        let mut config = match fs::read_to_string(path) {
+            Ok(s) => {
+                let mut config: Config = toml::from_str(&s)
+                    .map_err(|e| format!("Unable to parse the config file: {}", e))?;
+                if let Some(path) = &config.announcement.template {
+                    let template = fs::read_to_string(path)
+                        .map_err(|e| format!("Unable to read the template file: {}", e))?;
+                    Environment::new()
+                        .template_from_str(&template)
+                        .map_err(|e| format!("Unable to parse the template file: {}", e))?;
+                    config.announcement.loaded_template = Some(template);
+                }
+                RegexSet::new(&config.announcement.ignore)
+                    .map_err(|e| format!("Unable to parse the ignore patterns: {}", e))?;
+                if let Some(digest) = &config.digest {
+                    digest.weekday.parse::<Weekday>().map_err(|_| {
+                        format!("Unable to parse the digest weekday: {}", digest.weekday)
+                    })?;
+                    NaiveTime::parse_from_str(&digest.time, "%H:%M")
+                        .map_err(|e| format!("Unable to parse the digest time: {}", e))?;
+                }
+                config.daemon.schedule()?;
+                let mut names = std::collections::HashSet::new();
+                for project in &config.projects {
+                    if project.preset.is_none() && project.name.is_none() {
+                        return Err("Every project needs a `name` or a `preset`".to_string());
+                    }
+                    if !names.insert(project.state_dir(Path::new(""))) {
+                        return Err(format!("Project {} is configured twice", project.name()));
+                    }
+                }
+                if let Some(proxy) = &config.http.proxy {
+                    reqwest::Proxy::all(proxy)
+                        .map_err(|e| format!("Unable to parse the proxy: {}", e))?;
+                }
+                config
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(format!("Unable to read the config file: {}", e)),
+        };
+
+        config.discord.changelog_url = config.project(None).as_ref().and_then(changelog_url);
+        Ok(config)
+    }
+}
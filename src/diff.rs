@@ -0,0 +1,186 @@
+use std::collections::HashSet;
+
+// Whether the line is the header of the "Unreleased" section, which Keep a Changelog writes as
+// `## [Unreleased]`, while some changelogs leave out the brackets or write it in lower case.
+pub fn is_unreleased(line: &str) -> bool {
+    line.strip_prefix("## ").is_some_and(|s| {
+        s.trim()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .eq_ignore_ascii_case("unreleased")
+    })
+}
+
+// A line of the "Unreleased" section that matters to the diff: a sub-section header, or an entry
+// with the lines that continue it.
+enum Item<'a> {
+    Section(&'a str),
+    Entry(String),
+}
+
+// Find the entries of the "Unreleased" section of the new changelog that are not in the old one.
+// The result is a list of markdown lines, with each sub-section starting with a `## ` header.
+// Entries are compared by their normalized text, so that one which was only re-wrapped or had its
+// punctuation touched upstream, or which is in the section twice, isn't announced again.
+pub fn diff(old: &str, new: &str) -> Vec<String> {
+    let _span = tracing::info_span!("diff").entered();
+
+    // Store the changes in this vector.
+    let mut changes: Vec<String> = vec![];
+
+    // Without the "Unreleased" section in both, nothing can be told apart.
+    let (Some(old), Some(new)) = (unreleased(old), unreleased(new)) else {
+        tracing::warn!("Changelog has no unreleased section");
+        return changes;
+    };
+
+    let mut seen = entries(&old).map(|e| normalize(&e)).collect::<HashSet<_>>();
+    for item in new {
+        match item {
+            Item::Section(s) => {
+                // If the line starts a new sub-section while the last sub-section is empty,
+                // remove the last sub-section. Then add the new sub-section header.
+                if changes.last().is_some_and(|l| l.starts_with("## ")) {
+                    changes.pop();
+                }
+                tracing::trace!(title = s, "New sub-section");
+                changes.push("## ".to_string() + s);
+            }
+            Item::Entry(e) => match seen.insert(normalize(&e)) {
+                true => {
+                    tracing::trace!(entry = e, "New entry");
+                    changes.push(e);
+                }
+                false => tracing::trace!(entry = e, "Matched an entry that was seen"),
+            },
+        }
+    }
+
+    // If the last sub-section is empty, remove the last sub-section.
+    if changes.last().is_some_and(|l| l.starts_with("## ")) {
+        changes.pop();
+    }
+
+    tracing::debug!(lines = changes.len(), "Diffed changelog");
+    changes
+}
+
+// The entries of the "Unreleased" section of the old changelog that are nowhere in the new one,
+// which happens when the history is rewritten upstream. Entries that were released are still in
+// the new changelog, under their version.
+pub fn removed(old: &str, new: &str) -> Vec<String> {
+    let mut seen = entries(&items(new.lines()))
+        .map(|e| normalize(&e))
+        .collect::<HashSet<_>>();
+    entries(&unreleased(old).unwrap_or_default())
+        .filter(|e| seen.insert(normalize(e)))
+        .collect()
+}
+
+// The items of the "Unreleased" section of the changelog, if it has one.
+fn unreleased(changelog: &str) -> Option<Vec<Item<'_>>> {
+    let mut lines = changelog.split('\n');
+    if !lines.any(is_unreleased) {
+        return None;
+    }
+    Some(items(lines.take_while(|l| !l.starts_with("## "))))
+}
+
+// Join the lines of every entry, which continue until the next entry or header.
+fn items<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<Item<'a>> {
+    let mut items = vec![];
+    let mut in_entry = false;
+    for line in lines {
+        if let Some(s) = line.strip_prefix("### ") {
+            items.push(Item::Section(s.trim()));
+            in_entry = false;
+        } else if line.starts_with('#') {
+            in_entry = false;
+        } else if line.starts_with("- ") {
+            items.push(Item::Entry(line.trim_end().to_string()));
+            in_entry = true;
+        } else if line.trim().is_empty() {
+            // Blank lines end neither entries nor sub-sections.
+        } else if let Some(Item::Entry(e)) = items.last_mut().filter(|_| in_entry) {
+            tracing::trace!(line, entry = e, "Continuation of an entry");
+            e.push_str(&continuation(line));
+        } else {
+            tracing::trace!(line, "Skipped a line outside of any entry");
+        }
+    }
+    items
+}
+
+fn entries<'a>(items: &'a [Item<'_>]) -> impl Iterator<Item = String> + 'a {
+    items.iter().filter_map(|i| match i {
+        Item::Entry(e) => Some(e.clone()),
+        Item::Section(_) => None,
+    })
+}
+
+// The text of an entry that stays the same however it's wrapped: its words separated by single
+// spaces, without the punctuation at the end.
+fn normalize(entry: &str) -> String {
+    entry
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(['.', ',', ';', ':', '!'])
+        .to_string()
+}
+
+// A line that doesn't start an entry, as it's added to the end of the entry it belongs to. Nested
+// bullets go on their own line, indented by two spaces for each level, and anything else continues
+// the text.
+pub fn continuation(line: &str) -> String {
+    let text = line.trim_start();
+    match text.strip_prefix("- ").or_else(|| text.strip_prefix("* ")) {
+        Some(child) => {
+            let level = ((line.len() - text.len()) / 2).max(1);
+            format!("\n{}- {}", "  ".repeat(level), child)
+        }
+        None => format!(" {}", text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- Fishing rods\n- Boats\n\n### Fixed\n\n- A crash\n\n## [0.1.0] - 2024-01-01\n\n### Added\n\n- The world\n";
+
+    #[test]
+    fn new_entries_are_found() {
+        let new = OLD.replace("- Boats\n", "- Boats\n- Lanterns\n  that glow\n");
+        assert_eq!(diff(OLD, &new), ["## Added", "- Lanterns that glow"]);
+    }
+
+    #[test]
+    fn rewrapped_entries_are_not_new() {
+        let new = OLD.replace("- Fishing rods\n", "- Fishing\n  rods.\n");
+        assert!(diff(OLD, &new).is_empty());
+    }
+
+    #[test]
+    fn removed_entries_are_found() {
+        let new = OLD.replace("- Boats\n", "");
+        assert_eq!(removed(OLD, &new), ["- Boats"]);
+    }
+
+    #[test]
+    fn released_entries_are_not_removed() {
+        let new = OLD.replace(
+            "## [Unreleased]\n\n### Added\n\n- Fishing rods\n- Boats\n\n### Fixed\n\n- A crash\n\n",
+            "## [Unreleased]\n\n## [0.2.0] - 2024-02-01\n\n### Added\n\n- Fishing rods\n- Boats\n\n### Fixed\n\n- A crash\n\n",
+        );
+        assert!(removed(OLD, &new).is_empty());
+        assert!(diff(OLD, &new).is_empty());
+    }
+
+    #[test]
+    fn nested_bullets_continue_the_entry() {
+        assert_eq!(continuation("  - Child"), "\n  - Child");
+        assert_eq!(continuation("    * Grandchild"), "\n    - Grandchild");
+        assert_eq!(continuation("   more text"), " more text");
+    }
+}
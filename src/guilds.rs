@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::announcement::Announcement;
+use crate::state;
+
+const GUILDS_PATH: &str = "guilds.json";
+const SUBSCRIBERS_PATH: &str = "subscribers.json";
+const REVIEWS_PATH: &str = "reviews.json";
+
+// Number of direct messages in a row that can fail before the user is unsubscribed, as they have
+// most likely blocked the bot or left every guild that it shares with them.
+const MAX_FAILURES: u32 = 3;
+
+// Number of rejected announcements remembered.
+const MAX_REJECTED: usize = 100;
+
+// What the bot remembers about each guild: what the guild has set up with the bot's commands, as
+// opposed to the config file, which is only edited by whoever runs the bot, and what was posted.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GuildSettings {
+    // Role mentioned by every announcement, for guilds with an opt-in news role.
+    pub ping_role: Option<u64>,
+    // The message with the button that gives and takes the ping role, posted with
+    // `/setup role-menu`.
+    pub role_menu: Option<RoleMenu>,
+    // Whether announcements are paused in the guild.
+    pub paused: bool,
+    // Code of the language chosen with `/setup language`.
+    pub language: Option<String>,
+    // How the announcements are shown, chosen with `/setup style`.
+    pub style: Style,
+    // The latest announcement in each channel, by channel ID.
+    pub announcements: BTreeMap<u64, Posted>,
+    // Announcements that couldn't be posted, oldest first, which are retried on the next run.
+    pub undelivered: Vec<Undelivered>,
+    // The pages of the announcements that were too long for one message, by message ID.
+    pub pages: BTreeMap<u64, Vec<String>>,
+    // The announcements posted recently, oldest first, so that an announcement that is posted
+    // again by a retry or an overlapping run is recognized.
+    pub ledger: Vec<Delivered>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    // Every entry, with embeds for the devblogs and releases, a thread for the release notes, and
+    // buttons linking to the configured pages.
+    #[default]
+    Full,
+    // Every entry, but as text only.
+    Compact,
+    // Only the number of entries in each section, with the buttons linking to the changelog.
+    Digest,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RoleMenu {
+    pub channel: u64,
+    pub message: u64,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Posted {
+    pub message: u64,
+    // Unix timestamp.
+    pub posted_at: i64,
+    // The changes of the announcement, so that it can be edited to add more.
+    #[serde(default)]
+    pub changes: Vec<String>,
+    // The project that the announcement was about, whose announcements are the only ones that can
+    // be added to it.
+    #[serde(default)]
+    pub project: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Delivered {
+    // The hash of the announcement.
+    pub hash: String,
+    pub channel: u64,
+    pub messages: Vec<u64>,
+    // Unix timestamp.
+    pub posted_at: i64,
+    // The announcement as it was posted, which `/repost` posts again.
+    #[serde(default)]
+    pub announcement: Option<Announcement>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Undelivered {
+    pub announcement: Announcement,
+    // The channel that couldn't be posted to, or none if the guild's channels couldn't be found.
+    pub channel: Option<u64>,
+    // Unix timestamp of when the announcement was first queued.
+    pub queued_at: i64,
+}
+
+// A user who gets the announcements in direct messages, subscribed with `/subscribe`.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Subscriber {
+    // Code of the language of the user's Discord client when they subscribed.
+    pub language: Option<String>,
+    // Number of direct messages in a row that couldn't be sent.
+    pub failures: u32,
+}
+
+// The announcements of the staging channel.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Reviews {
+    // The announcements waiting for a review, or to be posted once approved, by the ID of the
+    // message with the buttons.
+    pub pending: BTreeMap<u64, Review>,
+    // The hashes of the rejected announcements, oldest first, which aren't staged again.
+    pub rejected: Vec<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Review {
+    pub project: String,
+    pub announcement: Announcement,
+    // The entries of the check to correct, and the state that the check staged, which are only
+    // made and saved once the announcement is approved.
+    pub retracted: Vec<String>,
+    pub staged: BTreeMap<String, Option<String>>,
+    // Unix timestamp.
+    pub staged_at: i64,
+    pub approved: bool,
+}
+
+impl Reviews {
+    // Drop the pending review of the message, remembering the announcement so that it isn't staged
+    // again.
+    pub fn reject(&mut self, message: u64) {
+        let Some(review) = self.pending.remove(&message) else {
+            return;
+        };
+        self.rejected.push(review.announcement.hash());
+        let excess = self.rejected.len().saturating_sub(MAX_REJECTED);
+        self.rejected.drain(..excess);
+    }
+}
+
+// The settings of every guild, the subscribers by user ID, and the reviews, which are saved as soon
+// as they are changed.
+pub struct Guilds {
+    path: PathBuf,
+    settings: Mutex<BTreeMap<u64, GuildSettings>>,
+    subscribers_path: PathBuf,
+    subscribers: Mutex<BTreeMap<u64, Subscriber>>,
+    reviews_path: PathBuf,
+    reviews: Mutex<Reviews>,
+}
+
+impl Guilds {
+    pub fn load(dir: impl Into<PathBuf>) -> Guilds {
+        let dir = dir.into();
+        let path = dir.join(GUILDS_PATH);
+        let settings = state::load_sealed(&path);
+        let subscribers_path = dir.join(SUBSCRIBERS_PATH);
+        let subscribers = state::load_sealed(&subscribers_path);
+        let reviews_path = dir.join(REVIEWS_PATH);
+        let reviews = state::load_sealed(&reviews_path);
+
+        Guilds {
+            path,
+            settings: Mutex::new(settings),
+            subscribers_path,
+            subscribers: Mutex::new(subscribers),
+            reviews_path,
+            reviews: Mutex::new(reviews),
+        }
+    }
+
+    pub fn get(&self, guild: u64) -> GuildSettings {
+        self.settings
+            .lock()
+            .unwrap()
+            .get(&guild)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn all(&self) -> BTreeMap<u64, GuildSettings> {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update(&self, guild: u64, f: impl FnOnce(&mut GuildSettings)) {
+        let mut settings = self.settings.lock().unwrap();
+        f(settings.entry(guild).or_default());
+
+        state::write_sealed(
+            &self.path,
+            &serde_json::to_string_pretty(&*settings).unwrap(),
+        );
+    }
+
+    pub fn subscribers(&self) -> BTreeMap<u64, Subscriber> {
+        self.subscribers.lock().unwrap().clone()
+    }
+
+    // Subscribe the user, returning whether they weren't subscribed yet.
+    pub fn subscribe(&self, user: u64, language: Option<String>) -> bool {
+        self.update_subscribers(|s| {
+            s.insert(
+                user,
+                Subscriber {
+                    language,
+                    failures: 0,
+                },
+            )
+            .is_none()
+        })
+    }
+
+    // Unsubscribe the user, returning whether they were subscribed.
+    pub fn unsubscribe(&self, user: u64) -> bool {
+        self.update_subscribers(|s| s.remove(&user).is_some())
+    }
+
+    // Record whether a direct message to the subscriber was sent, unsubscribing them once too many
+    // in a row have failed. Returns whether they were unsubscribed.
+    pub fn delivered(&self, user: u64, sent: bool) -> bool {
+        self.update_subscribers(|s| {
+            let Some(subscriber) = s.get_mut(&user) else {
+                return false;
+            };
+            subscriber.failures = match sent {
+                true => 0,
+                false => subscriber.failures + 1,
+            };
+            if subscriber.failures < MAX_FAILURES {
+                return false;
+            }
+            s.remove(&user);
+            true
+        })
+    }
+
+    fn update_subscribers<T>(&self, f: impl FnOnce(&mut BTreeMap<u64, Subscriber>) -> T) -> T {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let result = f(&mut subscribers);
+
+        state::write_sealed(
+            &self.subscribers_path,
+            &serde_json::to_string_pretty(&*subscribers).unwrap(),
+        );
+        result
+    }
+
+    pub fn reviews(&self) -> Reviews {
+        self.reviews.lock().unwrap().clone()
+    }
+
+    pub fn update_reviews<T>(&self, f: impl FnOnce(&mut Reviews) -> T) -> T {
+        let mut reviews = self.reviews.lock().unwrap();
+        let result = f(&mut reviews);
+
+        state::write_sealed(
+            &self.reviews_path,
+            &serde_json::to_string_pretty(&*reviews).unwrap(),
+        );
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::config::AnnouncementConfig;
+    use crate::config::ProjectConfig;
+    use crate::sources::Update;
+    use crate::state::test_dir;
+
+    #[test]
+    fn settings_are_kept() {
+        let dir = test_dir("guilds-kept");
+        Guilds::load(&dir).update(1, |g| g.paused = true);
+        assert!(Guilds::load(&dir).get(1).paused);
+    }
+
+    #[test]
+    fn damaged_files_are_moved_aside() {
+        let dir = test_dir("guilds-damaged");
+        Guilds::load(&dir).update(1, |g| g.paused = true);
+        let path = dir.join(GUILDS_PATH);
+        let file = fs::read_to_string(&path).unwrap();
+        fs::write(&path, &file[..file.len() - 4]).unwrap();
+
+        let guilds = Guilds::load(&dir);
+        assert!(!guilds.get(1).paused);
+        assert!(!path.exists());
+        assert!(dir.join("guilds.json.damaged").exists());
+    }
+
+    #[test]
+    fn unsealed_files_are_still_read() {
+        let dir = test_dir("guilds-unsealed");
+        fs::write(dir.join(SUBSCRIBERS_PATH), r#"{"2": {"failures": 1}}"#).unwrap();
+        assert_eq!(Guilds::load(&dir).subscribers()[&2].failures, 1);
+    }
+
+    #[test]
+    fn rejected_announcements_are_remembered() {
+        let dir = test_dir("guilds-rejected");
+        let updates = vec![Update::Section {
+            title: "Added".to_string(),
+            entries: vec!["- A thing".to_string()],
+        }];
+        let project = ProjectConfig::default();
+        let announcement =
+            Announcement::new(updates, &AnnouncementConfig::default(), &project).unwrap();
+        let hash = announcement.hash();
+
+        let review = Review {
+            project: project.name().to_string(),
+            announcement,
+            retracted: vec![],
+            staged: BTreeMap::from([("CHANGELOG.md".to_string(), Some(String::new()))]),
+            staged_at: 0,
+            approved: false,
+        };
+        Guilds::load(&dir).update_reviews(|r| r.pending.insert(3, review));
+        assert_eq!(Guilds::load(&dir).reviews().pending[&3].staged.len(), 1);
+
+        Guilds::load(&dir).update_reviews(|r| r.reject(3));
+        let reviews = Guilds::load(&dir).reviews();
+        assert!(reviews.pending.is_empty());
+        assert_eq!(reviews.rejected, [hash]);
+    }
+}
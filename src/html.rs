@@ -0,0 +1,76 @@
+use crate::sources::devblog::Devblog;
+
+// Render the announcement to html. Headers and bullets are the only markdown used by the changes,
+// where entries may have nested bullets on the following lines of the entry.
+pub fn render(changes: &[String], devblogs: &[Devblog]) -> String {
+    let mut html = String::new();
+    let mut in_list = false;
+
+    for line in changes {
+        if let Some(s) = line.strip_prefix("- ") {
+            if !in_list {
+                html.push_str("<ul>");
+                in_list = true;
+            }
+            html.push_str(&format!("<li>{}</li>", entry(s)));
+        } else {
+            if in_list {
+                html.push_str("</ul>");
+                in_list = false;
+            }
+            let s = line.trim_start_matches('#').trim();
+            html.push_str(&format!("<h2>{}</h2>", escape(s)));
+        }
+    }
+
+    if in_list {
+        html.push_str("</ul>");
+    }
+
+    if !devblogs.is_empty() {
+        html.push_str("<ul>");
+        for d in devblogs {
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>",
+                escape(&d.url),
+                escape(&d.title)
+            ));
+        }
+        html.push_str("</ul>");
+    }
+
+    html
+}
+
+// An entry with its nested bullets as nested lists.
+fn entry(s: &str) -> String {
+    let mut lines = s.split('\n');
+    let mut html = escape(lines.next().unwrap_or_default());
+
+    let mut depth = 0;
+    for line in lines {
+        let text = line.trim_start();
+        let level = (line.len() - text.len()) / 2;
+        let text = text.strip_prefix("- ").unwrap_or(text);
+
+        while depth < level {
+            html.push_str("<ul>");
+            depth += 1;
+        }
+        while depth > level.max(1) {
+            html.push_str("</ul>");
+            depth -= 1;
+        }
+        html.push_str(&format!("<li>{}</li>", escape(text)));
+    }
+    html.push_str(&"</ul>".repeat(depth));
+
+    html
+}
+
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,241 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use reqwest::header;
+use reqwest::Client;
+use reqwest::Proxy;
+use reqwest::Response;
+use reqwest::ResponseBuilderExt;
+use reqwest::StatusCode;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+
+use crate::config::HttpConfig;
+use crate::config::RetryConfig;
+use crate::retry::retry;
+use crate::state;
+use crate::state::State;
+
+// The last response of every URL that was downloaded, which is what offline mode reads instead.
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+// Build the client that every request is sent with, so that connections are reused. It can only be
+// built once, so the settings aren't changed by reloading the config.
+pub fn init(config: &HttpConfig) {
+    CLIENT.get_or_init(|| build(config));
+}
+
+// The shared client, with the default settings if `init` wasn't called.
+pub fn client() -> &'static Client {
+    CLIENT.get_or_init(|| build(&HttpConfig::default()))
+}
+
+fn build(config: &HttpConfig) -> Client {
+    let mut builder = Client::builder()
+        .user_agent(&config.user_agent)
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .timeout(Duration::from_secs(config.timeout_secs));
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(Proxy::all(proxy).expect("Unable to parse the proxy."));
+    }
+    builder.build().expect("Unable to build the HTTP client.")
+}
+
+// Read every download from the cache from now on.
+pub fn go_offline() {
+    OFFLINE.store(true, Ordering::Relaxed);
+}
+
+// Save the responses to `dir` instead of the cache. Everything is downloaded, even what hasn't
+// changed since the last run, so that replaying the run finds every response it needs.
+pub fn record(dir: &str) {
+    CACHE_DIR.get_or_init(|| dir.into());
+    RECORDING.store(true, Ordering::Relaxed);
+}
+
+// Read every download from the responses recorded to `dir`.
+pub fn replay(dir: &str) {
+    CACHE_DIR.get_or_init(|| dir.into());
+    go_offline();
+}
+
+pub fn offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+// The headers to send with the next request of a resource for the server to only return it if it
+// has changed. They are saved as the literal request header lines.
+pub struct Validators(String);
+
+impl Validators {
+    // Save the validators next to the cached file they belong to. This should only happen once the
+    // downloaded content has been fully processed, otherwise the next run would skip it.
+    pub fn save(&self, state: &State, name: &str) {
+        state.write(&validators_name(name), &self.0);
+    }
+}
+
+// Download `url` unless it hasn't changed since the cached file `name` was downloaded, in which case
+// nothing is returned. Offline, the cached response always counts as changed.
+pub async fn get_if_modified(
+    retry_config: &RetryConfig,
+    state: &State,
+    name: &str,
+    url: &str,
+) -> reqwest::Result<Option<(String, Validators)>> {
+    if offline() {
+        let body = cached(url)?.text().await?;
+        return Ok(Some((body, Validators(String::new()))));
+    }
+
+    // Without the cached file, the content is needed regardless of whether it changed.
+    let validators = match state.read(name) {
+        Ok(_) if RECORDING.load(Ordering::Relaxed) => String::new(),
+        Ok(_) => state.read(&validators_name(name)).unwrap_or_default(),
+        Err(_) => String::new(),
+    };
+
+    let response = retry(retry_config, || async {
+        let mut request = client().get(url);
+        for (k, v) in validators.lines().filter_map(|l| l.split_once(": ")) {
+            request = request.header(k, v);
+        }
+        request.send().await?.error_for_status()
+    })
+    .await?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+
+    let mut validators = String::new();
+    if let Some(etag) = response.headers().get(header::ETAG) {
+        if let Ok(s) = etag.to_str() {
+            validators += &format!("{}: {}\n", header::IF_NONE_MATCH, s);
+        }
+    }
+    if let Some(date) = response.headers().get(header::LAST_MODIFIED) {
+        if let Ok(s) = date.to_str() {
+            validators += &format!("{}: {}\n", header::IF_MODIFIED_SINCE, s);
+        }
+    }
+
+    let body = response.text().await?;
+    cache(url, body.as_bytes());
+    Ok(Some((body, Validators(validators))))
+}
+
+// Download `url`, or read its cached response offline. The response has already been read, so
+// only its body is left.
+pub async fn get(retry_config: &RetryConfig, url: &str) -> reqwest::Result<Response> {
+    if offline() {
+        return cached(url);
+    }
+
+    let body = retry(retry_config, || async {
+        client()
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await
+    })
+    .await?;
+    cache(url, &body);
+    Ok(Response::from(::http::Response::new(body)))
+}
+
+// Whether `url` exists, without downloading it. Only whether it did is recorded.
+pub async fn head(retry_config: &RetryConfig, url: &str) -> reqwest::Result<()> {
+    let path = cache_path(url);
+    if offline() {
+        return match path.exists() {
+            true => Ok(()),
+            false => Err(not_found(url)),
+        };
+    }
+
+    let response = retry(retry_config, || async {
+        client().head(url).send().await?.error_for_status()
+    })
+    .await;
+    match response {
+        Ok(_) => cache(url, b""),
+        Err(_) => {
+            fs::remove_file(path).ok();
+        }
+    }
+    response.map(|_| ())
+}
+
+// Download and parse a JSON document.
+pub async fn get_json<T: DeserializeOwned>(
+    retry_config: &RetryConfig,
+    url: &str,
+) -> reqwest::Result<T> {
+    get(retry_config, url).await?.json::<T>().await
+}
+
+// The cached response of `url`. Nothing can be checked offline without it, so a missing response
+// fails the download rather than being downloaded.
+fn cached(url: &str) -> reqwest::Result<Response> {
+    let path = cache_path(url);
+    match fs::read(&path) {
+        Ok(body) => Ok(Response::from(::http::Response::new(body))),
+        Err(e) => {
+            tracing::error!(
+                url,
+                path = %path.display(),
+                error = %e,
+                "Unable to read the cached response in offline mode"
+            );
+            Err(not_found(url))
+        }
+    }
+}
+
+// The error of a download of `url` that wasn't found, as if the server had answered that.
+fn not_found(url: &str) -> reqwest::Error {
+    let mut builder = ::http::Response::builder().status(StatusCode::NOT_FOUND);
+    if let Ok(url) = Url::parse(url) {
+        builder = builder.url(url);
+    }
+    let response = builder.body("").unwrap();
+    Response::from(response).error_for_status().unwrap_err()
+}
+
+fn cache(url: &str, body: &[u8]) {
+    fs::create_dir_all(cache_dir()).expect("Unable to create the cache directory.");
+    state::write_atomic(&cache_path(url), body);
+}
+
+fn cache_dir() -> &'static Path {
+    CACHE_DIR.get_or_init(state::cache_dir)
+}
+
+// The URL with anything that can't be in a file name replaced.
+fn cache_path(url: &str) -> PathBuf {
+    let name = url
+        .trim_start_matches("https://")
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    cache_dir().join(name)
+}
+
+fn validators_name(name: &str) -> String {
+    name.to_string() + ".validators"
+}
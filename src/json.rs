@@ -0,0 +1,78 @@
+use regex::Regex;
+use serde::Serialize;
+
+use crate::announcement::Announcement;
+use crate::sources::artifacts::Artifacts;
+use crate::sources::devblog::Devblog;
+use crate::sources::twitch::Stream;
+
+// The changes parsed from the sources, for other tools to use without scraping the announcements.
+#[derive(Serialize)]
+struct Changes<'a> {
+    // The commit of the changelog that the changes were parsed from, if it could be found.
+    commit: Option<&'a str>,
+    sections: Vec<Section<'a>>,
+    releases: Vec<&'a str>,
+    artifacts: &'a [Artifacts],
+    devblogs: &'a [Devblog],
+    streams: &'a [Stream],
+}
+
+#[derive(Serialize)]
+struct Section<'a> {
+    title: &'a str,
+    entries: Vec<Entry<'a>>,
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    text: &'a str,
+    // The targets of the markdown links and bare URLs in the entry.
+    links: Vec<&'a str>,
+}
+
+// Render the announcement as JSON. Without an announcement, there are no changes.
+pub fn render(announcement: Option<&Announcement>, commit: Option<&str>) -> String {
+    let links = Regex::new(r"\]\(([^)\s]+)\)|(?:^|[\s(<])(https?://[^\s)>]+)").unwrap();
+
+    let mut changes = Changes {
+        commit,
+        sections: vec![],
+        releases: vec![],
+        artifacts: &[],
+        devblogs: &[],
+        streams: &[],
+    };
+
+    if let Some(a) = announcement {
+        changes.sections = a
+            .sections()
+            .into_iter()
+            // The released versions, their downloads, the streams and the devblogs are listed on
+            // their own.
+            .filter(|s| !["Released", "Downloads", "Live"].contains(&s.title))
+            .filter(|s| !s.entries.is_empty())
+            .map(|s| Section {
+                title: s.title,
+                entries: s
+                    .entries
+                    .into_iter()
+                    .map(|text| Entry {
+                        text,
+                        links: links
+                            .captures_iter(text)
+                            .filter_map(|c| c.get(1).or(c.get(2)))
+                            .map(|m| m.as_str())
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        changes.releases = a.releases.iter().map(|r| r.version.as_str()).collect();
+        changes.artifacts = &a.artifacts;
+        changes.devblogs = &a.devblogs;
+        changes.streams = &a.streams;
+    }
+
+    serde_json::to_string_pretty(&changes).unwrap()
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+// The bundles of strings, by the code of their language. The codes are the locales of Discord, so
+// that the slash commands can be localized with the same bundles.
+const BUNDLES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.toml")),
+    ("de", include_str!("../locales/de.toml")),
+    ("fr", include_str!("../locales/fr.toml")),
+];
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Bundle {
+    name: String,
+    title: Option<String>,
+    sections: BTreeMap<String, String>,
+    messages: BTreeMap<String, String>,
+    commands: BTreeMap<String, String>,
+}
+
+fn bundles() -> &'static BTreeMap<&'static str, Bundle> {
+    static BUNDLES_PARSED: OnceLock<BTreeMap<&str, Bundle>> = OnceLock::new();
+    BUNDLES_PARSED.get_or_init(|| {
+        BUNDLES
+            .iter()
+            .map(|(code, s)| (*code, toml::from_str(s).expect("Unable to parse locale.")))
+            .collect()
+    })
+}
+
+// The bundle of the language, or the English one if there is none.
+fn bundle(language: Option<&str>) -> &'static Bundle {
+    let bundles = bundles();
+    language
+        .and_then(|l| bundles.get(l))
+        .unwrap_or_else(|| &bundles[DEFAULT_LANGUAGE])
+}
+
+// The codes and names of every language with a bundle.
+pub fn languages() -> Vec<(&'static str, &'static str)> {
+    bundles()
+        .iter()
+        .map(|(code, b)| (*code, b.name.as_str()))
+        .collect()
+}
+
+pub fn exists(language: &str) -> bool {
+    bundles().contains_key(language)
+}
+
+// The heading of the announcements.
+pub fn title(language: Option<&str>) -> String {
+    bundle(language)
+        .title
+        .clone()
+        .or_else(|| bundle(None).title.clone())
+        .unwrap_or_default()
+}
+
+// The names of the sections, by their English titles. Sections that are left out keep their title.
+pub fn sections(language: Option<&str>) -> BTreeMap<String, String> {
+    bundle(language).sections.clone()
+}
+
+// The message with the given key, with every `{name}` replaced by the value of the argument. Keys
+// that are left out of the bundle are looked up in the English one.
+pub fn text(language: Option<&str>, key: &str, args: &[(&str, &str)]) -> String {
+    let message = bundle(language)
+        .messages
+        .get(key)
+        .or_else(|| bundle(None).messages.get(key))
+        .map_or(key, |s| s.as_str());
+
+    args.iter().fold(message.to_string(), |s, (name, value)| {
+        s.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+// The English description of a slash command, subcommand or option, and its translations by
+// language code.
+pub fn description(key: &str) -> (String, Vec<(&'static str, &'static str)>) {
+    let english = bundle(None).commands.get(key).cloned().unwrap_or_default();
+    let translations = bundles()
+        .iter()
+        .filter(|(code, _)| **code != DEFAULT_LANGUAGE)
+        .filter_map(|(code, b)| Some((*code, b.commands.get(key)?.as_str())))
+        .collect();
+
+    (english, translations)
+}
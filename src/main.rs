@@ -1,192 +1,889 @@
-use std::fs;
-use std::io;
+#[cfg(feature = "http-server")]
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-use scraper::Html;
-use scraper::Selector;
+use clap::Parser;
+use clap::Subcommand;
+use clap::ValueEnum;
+use tokio::signal::unix::signal;
+use tokio::signal::unix::SignalKind;
+use tokio::sync::Notify;
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
 
-use serenity::client::bridge::gateway::ShardManager;
-use serenity::model::gateway::Ready;
-use serenity::prelude::*;
+use activity::Activity;
+use announcement::Announcement;
+use config::Config;
+use config::ProjectConfig;
+use config::StagingConfig;
+use guilds::Guilds;
+use guilds::Review;
+use metrics::METRICS;
+use pending::Pending;
+use sinks::discord;
+use sinks::discord::Gateway;
+use sinks::Delivery;
+use sinks::Discord;
+use sources::changelog;
+use sources::devblog;
+use sources::Update;
+use state::State;
 
-const CHANGELOG_PATH: &str = "CHANGELOG.md";
-const CHANGELOG_URL: &str = "https://gitlab.com/veloren/veloren/-/raw/weekly/CHANGELOG.md";
+mod activity;
+mod announcement;
+mod config;
+mod diff;
+mod guilds;
+mod html;
+mod http;
+mod json;
+mod locale;
+mod metrics;
+mod pending;
+mod retry;
+#[cfg(feature = "http-server")]
+mod server;
+mod sinks;
+mod sources;
+mod state;
+mod summary;
+mod translate;
 
-const DEVBLOGS_PATH: &str = "DEVBLOGS.md";
-const DEVBLOGS_URL: &str = "https://veloren.net/blog/";
+// Exit statuses of a check: changes were posted, nothing new was found or announced, something
+// couldn't be downloaded, or the changes couldn't be delivered everywhere and are announced again
+// on the next run.
+const EXIT_POSTED: i32 = 0;
+const EXIT_FETCH_FAILED: i32 = 1;
+const EXIT_UNDELIVERED: i32 = 3;
+const EXIT_UNCHANGED: i32 = 4;
 
-const UNRELEASED_HEADER: &str = "## [Unreleased]";
+// Exit status when another instance is already running (EX_TEMPFAIL).
+const EXIT_LOCKED: i32 = 75;
 
-// There is definitely a way of doing this without abusing unsafe but I cannot currently find a way
-// to achieve that. *Surely* this doesn't come back to bite me. :D
-static mut SHARD_MANAGER: Option<Arc<Mutex<ShardManager>>> = None;
+// Exit status when the bot isn't configured properly (EX_CONFIG).
+const EXIT_CONFIG: i32 = 78;
+
+#[derive(Parser)]
+#[command(
+    about,
+    version,
+    after_help = "A check exits with 0 if changes were posted, 4 if nothing new was announced, \
+                  1 if something couldn't be downloaded, or 3 if the changes couldn't be \
+                  delivered everywhere."
+)]
+struct Args {
+    /// Path to the config file.
+    #[arg(long, default_value = config::CONFIG_PATH)]
+    config: String,
+
+    /// File holding the Discord bot token, instead of the `DISCORD_TOKEN` environment variable,
+    /// the systemd credential `discord-token`, or the `DISCORD_TOKEN` file.
+    #[arg(long)]
+    token_file: Option<String>,
+
+    /// Directory of the files remembering what was announced. Defaults to the `StateDirectory=`
+    /// of the systemd service, or `$XDG_STATE_HOME/veloren-changelog`, where the files of older
+    /// versions are moved from the working directory.
+    #[arg(long, global = true)]
+    state_dir: Option<PathBuf>,
+
+    /// Read every download from the responses cached by previous runs, instead of the network.
+    /// Sources that need to log in are left out.
+    #[arg(long, global = true, conflicts_with_all = ["record", "replay"])]
+    offline: bool,
+
+    /// Save every response of the run to this directory, to replay the run later.
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<String>,
+
+    /// Read every download from the responses recorded to this directory, like `--offline`.
+    #[arg(long, global = true, value_name = "DIR")]
+    replay: Option<String>,
+
+    /// Log more: `-v` for debugging, `-vv` also for every decision of the changelog diff.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Only log warnings and errors, so that a run without changes prints nothing.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Append the rendered announcements to this file instead of posting them anywhere. Only the
+    /// daemon still needs the Discord token then, for the slash commands.
+    #[arg(long, global = true, value_name = "FILE")]
+    capture: Option<String>,
+
+    /// The project that `backfill` and `diff` are about, by name. Defaults to the first configured
+    /// project.
+    #[arg(long, global = true, value_name = "NAME")]
+    project: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Re-announce a range of past posts or releases.
+    Backfill {
+        /// What to re-announce.
+        #[arg(value_enum, default_value_t = BackfillSource::Devblogs)]
+        source: BackfillSource,
+
+        /// The oldest post or version to re-announce.
+        #[arg(long)]
+        from: String,
+
+        /// The newest post or version to re-announce. Defaults to the latest one.
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Print the announcements instead of posting them.
+        #[arg(long)]
+        print: bool,
+    },
+    /// Print the changes between two versions of the changelog.
+    Diff {
+        /// The version to compare from, whose own changes are left out.
+        from: String,
+
+        /// The version to compare to. Defaults to the latest version.
+        to: Option<String>,
+
+        /// How to print the changes.
+        #[arg(long, value_enum, default_value_t = Format::Markdown)]
+        format: Format,
+    },
+    /// Print what the next check would find, without posting or saving anything.
+    Fetch {
+        /// How to print the changes.
+        #[arg(long, value_enum, default_value_t = Format::Markdown)]
+        format: Format,
+    },
+    /// Keep running and check for changes periodically.
+    Daemon,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Markdown,
+    /// The sections, entries and their links, releases and their downloads, devblogs, and the commit
+    /// of the changelog.
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BackfillSource {
+    /// Devblogs, as one announcement.
+    Devblogs,
+    /// Versions of the changelog, as one announcement each.
+    Releases,
+}
 
 #[tokio::main]
 async fn main() -> reqwest::Result<()> {
-    let changelog_old = match read_changelog().await {
-        Ok(s) => s,
-        Err(_) => download_changelog().await?,
-    };
-
-    let changelog_new = download_changelog().await?;
-
-    // Store the changes in this vector.
-    let mut changes: Vec<String> = vec![];
-
-    // Skip to the "Unreleased" section.
-    let mut old = changelog_old.split('\n').peekable();
-    while old.next().unwrap() != UNRELEASED_HEADER {}
-    while old.peek().unwrap().is_empty() || old.peek().unwrap().starts_with("### ") {
-        old.next();
-    }
-
-    let mut new = changelog_new.split('\n');
-    while new.next().unwrap() != UNRELEASED_HEADER {}
-
-    // Find the lines in "new" that do not exist in "old".
-    for line in new {
-        if line.starts_with("## ") {
-            // Start of first versioned section.
-            break;
-        } else if line.is_empty() {
-            // Don't add blank lines automatically.
-            continue;
-        } else if let Some(s) = line.strip_prefix("### ") {
-            // If the line starts a new sub-section while the last sub-section is empty, remove the
-            // last sub-section. Then add the new sub-section header.
-            if let Some(s) = changes.last() {
-                if s.starts_with("## ") {
-                    changes.pop();
-                }
-            }
-            changes.push("## ".to_string() + s)
-        } else if &line != old.peek().unwrap() {
-            // If the new line is not equal to the old line, add it. However, if the line does not
-            // start with a bullet point, add it to the previous line.
-            if line.starts_with("- ") {
-                changes.push(line.to_string());
-            } else {
-                changes.last_mut().unwrap().push_str(&line[1..]);
-            }
-        } else {
-            // If the two lines are equal, advance both of them. Also keep advancing the old
-            // iterator over empty lines and sub-section headers.
-            old.next();
-            while old.peek().unwrap().is_empty() || old.peek().unwrap().starts_with("### ") {
-                old.next();
+    let args = Args::parse();
+    let config = Config::try_load(&args.config).unwrap_or_else(|e| {
+        // Logging is configured by the config, so there is nothing to log to yet.
+        eprintln!("{}", e);
+        std::process::exit(EXIT_CONFIG);
+    });
+    init_logging(&config, &args);
+    http::init(&config.http);
+    if args.offline {
+        http::go_offline();
+    }
+    if let Some(dir) = &args.record {
+        http::record(dir);
+    }
+    if let Some(dir) = &args.replay {
+        http::replay(dir);
+    }
+    if let Some(path) = &args.capture {
+        sinks::capture(path);
+    }
+
+    let Some(project) = config.project(args.project.as_deref()) else {
+        tracing::error!(
+            project = args.project,
+            "No project of that name is configured"
+        );
+        std::process::exit(EXIT_CONFIG);
+    };
+
+    let state_dir = args.state_dir.clone().unwrap_or_else(state::default_dir);
+    state::prepare(&state_dir);
+
+    // Printing needs neither Discord nor the lock.
+    match &args.command {
+        Some(Command::Backfill {
+            source,
+            from,
+            to,
+            print: true,
+        }) => {
+            for announcement in backfill(&config, &project, *source, from, to.as_deref()).await? {
+                println!("{}\n", announcement.message());
             }
+            return Ok(());
+        }
+        Some(Command::Diff { from, to, format }) => {
+            return diff_versions(&config, &project, from, to.as_deref(), *format).await;
         }
+        Some(Command::Fetch { format }) => return fetch(&config, &state_dir, *format).await,
+        _ => {}
     }
 
-    // If the last sub-section is empty, remove the last sub-section.
-    if let Some(s) = changes.last() {
-        if s.starts_with("## ") {
-            changes.pop();
+    let token = match discord::token(args.token_file.as_deref()) {
+        Ok(t) => t,
+        Err(_) if args.capture.is_some() && !matches!(args.command, Some(Command::Daemon)) => {
+            String::new()
+        }
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(EXIT_CONFIG);
+        }
+    };
+
+    let state = State::new(&state_dir);
+    let guilds = Arc::new(Guilds::load(&state_dir));
+
+    // Overlapping runs would diff against the same old state and post the same changes twice.
+    let Some(_lock) = state.lock() else {
+        tracing::info!("Another instance is already running");
+        std::process::exit(EXIT_LOCKED);
+    };
+
+    match args.command {
+        None => {
+            let outcome = run(&config, &state_dir, Discord::Token(token, guilds)).await;
+            std::process::exit(outcome.exit_code());
+        }
+        Some(Command::Backfill {
+            source, from, to, ..
+        }) => {
+            for announcement in backfill(&config, &project, source, &from, to.as_deref()).await? {
+                announce(
+                    &config,
+                    announcement,
+                    Discord::Token(token.clone(), guilds.clone()),
+                    &state_dir,
+                )
+                .await;
+            }
+            Ok(())
         }
+        Some(Command::Daemon) => daemon(&args.config, config, &state_dir, &token, guilds).await,
+        Some(Command::Diff { .. } | Command::Fetch { .. }) => unreachable!(),
     }
+}
 
-    // Check for new devblogs.
-    let devblogs_old = match read_devblogs().await {
-        Ok(s) => s,
-        Err(_) => download_devblogs().await?,
+// Log to stderr at the configured level, or the one of the flags. `RUST_LOG` overrides both if set.
+// The verbose levels only apply to the bot itself, since its dependencies log a lot more.
+fn init_logging(config: &Config, args: &Args) {
+    let level = match (args.quiet, args.verbose) {
+        (true, _) => "warn",
+        (false, 0) => &config.log.level,
+        (false, 1) => "info,veloren_changelog=debug",
+        (false, _) => "info,veloren_changelog=trace",
     };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
 
-    let devblogs_new = download_devblogs().await?;
+    if config.log.json {
+        builder.json().init();
+    } else {
+        builder.init();
+    }
+}
 
-    // Exctract only the new devblogs.
-    let old = devblogs_old.split('\n').next().unwrap();
-    let mut new = devblogs_new
-        .split('\n')
-        .take_while(|s| s != &old)
-        .map(|s| "- ".to_string() + s)
-        .collect::<Vec<_>>();
+// What a check did. The outcome of checking several projects is the worst of their outcomes, in the
+// order of the variants.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Outcome {
+    Unchanged,
+    Posted,
+    // Not everything was delivered, so the state wasn't saved.
+    Undelivered,
+    // A source couldn't be downloaded, so nothing was checked.
+    FetchFailed,
+}
 
-    if !new.is_empty() {
-        changes.push("## Blog post(s)".to_string());
-        changes.append(&mut new);
+impl Outcome {
+    fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Unchanged => EXIT_UNCHANGED,
+            Outcome::Posted => EXIT_POSTED,
+            Outcome::Undelivered => EXIT_UNDELIVERED,
+            Outcome::FetchFailed => EXIT_FETCH_FAILED,
+        }
+    }
+}
+
+// Check every project for new changelog entries and devblogs, and announce them. A project that
+// can't be checked doesn't keep the others from being checked.
+async fn run(config: &Config, state_dir: &Path, discord: Discord) -> Outcome {
+    // Announcements that some targets missed go out before anything newer.
+    for sink in sinks::from_config(config, discord.clone(), state_dir) {
+        let span = tracing::info_span!("redeliver", sink = sink.name());
+        let delivery = sink.redeliver().instrument(span).await;
+        METRICS.published(sink.name(), &delivery);
+    }
+
+    let mut outcome = Outcome::Unchanged;
+    for project in config.projects() {
+        // Anything staged by a failed check must not be mistaken for the old state by the next.
+        let state = project_state(&project, state_dir);
+        let span = tracing::info_span!("project", project = project.name());
+        let checked = check(config, &project, &state, discord.clone(), state_dir)
+            .instrument(span.clone())
+            .await
+            .unwrap_or_else(|e| {
+                span.in_scope(|| tracing::error!(error = %e, "Check failed"));
+                Outcome::FetchFailed
+            });
+        if checked > outcome {
+            outcome = checked;
+        }
     }
 
-    // If any changes have occured, message the channel.
-    if !changes.is_empty() {
-        let discord_token = fs::read_to_string("DISCORD_TOKEN").unwrap();
-        let mut client = Client::builder(
-            &discord_token,
-            serenity::model::gateway::GatewayIntents::default(),
-        )
-        .event_handler(Handler {
-            message: "# Veloren News!\n".to_string() + &changes.join("\n"),
+    if outcome < Outcome::Undelivered {
+        METRICS.succeeded();
+    }
+    outcome
+}
+
+// The state of a project, in its own directory.
+fn project_state(project: &ProjectConfig, state_dir: &Path) -> State {
+    let dir = project.state_dir(state_dir);
+    std::fs::create_dir_all(&dir).expect("Unable to create the state directory.");
+    State::new(dir)
+}
+
+// Check a project and announce what is new. The new state is saved unless the outcome is
+// `Undelivered`.
+async fn check(
+    config: &Config,
+    project: &ProjectConfig,
+    state: &State,
+    discord: Discord,
+    state_dir: &Path,
+) -> reqwest::Result<Outcome> {
+    // An announcement waiting for a review holds back the project until it's reviewed, since the
+    // check would only find it again.
+    let staging = config
+        .discord
+        .staging
+        .as_ref()
+        .filter(|_| !sinks::capturing());
+    if let Some(staging) = staging {
+        let pending = discord
+            .guilds()
+            .reviews()
+            .pending
+            .into_iter()
+            .find(|(_, r)| r.project == project.name());
+        if let Some((message, review)) = pending {
+            return Ok(reviewed(config, staging, state, discord, state_dir, message, review).await);
+        }
+    }
+
+    let mut updates = vec![];
+    for source in sources::from_config(config, project) {
+        let span = tracing::info_span!("fetch", source = source.name());
+        let result = source.poll(state).instrument(span).await;
+        METRICS.polled(source.name(), &result);
+        let new = result?;
+        tracing::info!(source = source.name(), updates = new.len(), "Polled source");
+        updates.extend(new);
+    }
+
+    // Corrections don't wait for the digest, since the announcements that they correct are out. They
+    // are only made once the state is saved, otherwise they would be made again by the next check.
+    let (retracted, updates): (Vec<_>, Vec<_>) = updates
+        .into_iter()
+        .partition(|u| matches!(u, Update::Retracted(_)));
+    let retracted = retracted
+        .into_iter()
+        .flat_map(|u| match u {
+            Update::Retracted(entries) => entries,
+            _ => vec![],
         })
-        .await
-        .expect("Unable to start the bot.");
+        .collect::<Vec<_>>();
+
+    // Updates held back for the digest are saved with the rest of the state, so that they are
+    // found again if anything goes wrong before they are announced.
+    let now = chrono::Utc::now();
+    let mut pending = Pending::load(state);
+    pending.add(updates, now);
+    if !pending.due(config.digest.as_ref(), config.batch.as_ref(), now) {
+        pending.save(state);
+        state.commit();
+        correct(config, &retracted, discord, state_dir).await;
+        return Ok(Outcome::Unchanged);
+    }
 
-        // Save the shard manager for shutting down soon(tm). See note by SHARD_MANAGER for more
-        // information about this unsafe block.
-        unsafe {
-            SHARD_MANAGER = Some(client.shard_manager.clone());
+    let updates = pending.take();
+    pending.save(state);
+
+    // If any changes have occured, message the channel. The new state is only saved once the
+    // announcement has been delivered everywhere, otherwise it would never be announced again.
+    // Targets that the sink has queued the announcement for don't count, as it still reaches them.
+    let mut outcome = Outcome::Unchanged;
+    if let Some(mut announcement) = Announcement::new(updates, &config.announcement, project) {
+        if let Some(c) = &config.summary {
+            announcement.summary = summary::summarize(c, &announcement).await;
+        }
+
+        // Every digest covers the same time, so their activity can be compared.
+        if let Some(digest) = &config.digest {
+            let mut activity = Activity::load(state);
+            activity.add(&announcement, now.date_naive());
+            activity.save(state);
+            announcement.chart = digest.chart_weeks.and_then(|w| activity.chart(w));
         }
 
-        if let Err(e) = client.start().await {
-            println!("Bot crashed due to error: {:?}", e);
+        // The announcement is kept with the staged state until it's reviewed. Rejected
+        // announcements are dropped without saving the state, so that the changes are found again
+        // on the next check, but are only staged again once they have changed, e.g. once the
+        // changelog has been fixed.
+        if let Some(staging) = staging {
+            let guilds = discord.guilds();
+            if guilds.reviews().rejected.contains(&announcement.hash()) {
+                tracing::info!("Announcement was rejected, not saving the state");
+                return Ok(Outcome::Unchanged);
+            }
+
+            let retry = &config.retry;
+            let message =
+                discord::stage(&config.discord, retry, staging, &announcement, discord).await;
+            let Some(message) = message else {
+                return Ok(Outcome::Undelivered);
+            };
+            let review = Review {
+                project: project.name().to_string(),
+                announcement,
+                retracted,
+                staged: state.staged(),
+                staged_at: now.timestamp(),
+                approved: false,
+            };
+            guilds.update_reviews(|r| r.pending.insert(message, review));
+            return Ok(Outcome::Unchanged);
         }
+
+        let delivery = announce(config, announcement, discord.clone(), state_dir).await;
+        if !delivery.failed.is_empty() {
+            tracing::warn!(
+                failed = delivery.failed.len(),
+                "Not saving the state, the changes will be announced again on the next run"
+            );
+            return Ok(Outcome::Undelivered);
+        }
+        outcome = Outcome::Posted;
     }
 
-    Ok(())
+    state.commit();
+    correct(config, &retracted, discord, state_dir).await;
+    Ok(outcome)
+}
+
+// Announce the announcement of the project that was staged for review once it's approved, saving
+// the state that it was found with. It's dropped if nobody reviews it in time, to be staged again
+// by a later check.
+async fn reviewed(
+    config: &Config,
+    staging: &StagingConfig,
+    state: &State,
+    discord: Discord,
+    state_dir: &Path,
+    message: u64,
+    review: Review,
+) -> Outcome {
+    let guilds = discord.guilds();
+    if !review.approved {
+        let waited = chrono::Utc::now().timestamp() - review.staged_at;
+        if waited <= staging.timeout_hours as i64 * 3600 {
+            tracing::info!("Announcement is waiting for a review");
+            return Outcome::Unchanged;
+        }
+
+        tracing::warn!("Nobody reviewed the announcement in time, dropping it");
+        guilds.update_reviews(|r| r.pending.remove(&message));
+        discord::expire(&config.discord, &config.retry, staging, message, discord).await;
+        return Outcome::Unchanged;
+    }
+
+    state.restore(review.staged);
+    let delivery = announce(config, review.announcement, discord.clone(), state_dir).await;
+    if !delivery.failed.is_empty() {
+        tracing::warn!(
+            failed = delivery.failed.len(),
+            "Not saving the state, the approved announcement will be announced again on the next run"
+        );
+        return Outcome::Undelivered;
+    }
+
+    state.commit();
+    guilds.update_reviews(|r| r.pending.remove(&message));
+    correct(config, &review.retracted, discord, state_dir).await;
+    Outcome::Posted
 }
 
-async fn download_changelog() -> reqwest::Result<String> {
-    let md = reqwest::get(CHANGELOG_URL).await?.text().await?;
-    fs::write(CHANGELOG_PATH, &md).expect("Unable to write to file.");
-    Ok(md)
+// Check for changes every interval until the process is killed, staying connected to Discord in
+// between. A failed check is logged and retried on the next interval. The config is reloaded on
+// SIGHUP, except for the interval and the HTTP settings, which need a restart. A check can also be
+// requested over HTTP between the scheduled ones.
+async fn daemon(
+    config_path: &str,
+    mut config: Config,
+    state_dir: &Path,
+    token: &str,
+    guilds: Arc<Guilds>,
+) -> reqwest::Result<()> {
+    let interval = Duration::from_secs(config.daemon.interval_secs);
+
+    let requested = Arc::new(Notify::new());
+    if let Some(addr) = &config.daemon.http_addr {
+        serve(
+            addr,
+            &config,
+            period(&config).unwrap_or(interval),
+            requested.clone(),
+        );
+    }
+
+    let discord = config.discord.clone();
+    let retry = config.retry.clone();
+    let gateway = Gateway::connect(token, guilds, discord, retry, true, Some(requested.clone()));
+    let Some(gateway) = gateway.await else {
+        tracing::error!("Unable to connect to Discord");
+        return Ok(());
+    };
+    let gateway = Arc::new(gateway);
+
+    let mut hangup = signal(SignalKind::hangup()).expect("Unable to listen for SIGHUP.");
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = next_check(&config, &mut interval) => {}
+            _ = requested.notified() => tracing::info!("Checking for changes as requested"),
+            _ = hangup.recv() => {
+                match Config::try_load(config_path) {
+                    Ok(c) => {
+                        config = c;
+                        tracing::info!(path = config_path, "Reloaded the config");
+                    }
+                    Err(e) => tracing::error!(error = e, "Keeping the old config"),
+                }
+                continue;
+            }
+        }
+
+        run(&config, state_dir, Discord::Gateway(gateway.clone())).await;
+    }
 }
 
-async fn read_changelog() -> io::Result<String> {
-    fs::read_to_string(CHANGELOG_PATH)
+// Wait until the next time of the schedule, or for the interval if there is no schedule. Unlike the
+// interval, the schedule can be changed by reloading the config.
+async fn next_check(config: &Config, interval: &mut tokio::time::Interval) {
+    let schedule = config
+        .daemon
+        .schedule()
+        .expect("Unable to parse the schedule.");
+    let Some((schedule, timezone)) = schedule else {
+        interval.tick().await;
+        return;
+    };
+
+    match schedule.upcoming(timezone).next() {
+        Some(next) => {
+            tracing::info!(at = %next, "Waiting for the next scheduled check");
+            let wait = next.with_timezone(&chrono::Utc) - chrono::Utc::now();
+            tokio::time::sleep(wait.to_std().unwrap_or_default()).await;
+        }
+        None => {
+            tracing::warn!("The schedule has no upcoming times");
+            std::future::pending::<()>().await;
+        }
+    }
 }
 
-async fn download_devblogs() -> reqwest::Result<String> {
-    let html = reqwest::get(DEVBLOGS_URL).await?.text().await?;
-    let selector = Selector::parse(".header-link").unwrap();
+// The time between the next two checks of the schedule, which stands in for the interval.
+fn period(config: &Config) -> Option<Duration> {
+    let (schedule, timezone) = config.daemon.schedule().ok()??;
+    let mut upcoming = schedule.upcoming(timezone);
+    let (first, second) = (upcoming.next()?, upcoming.next()?);
+    (second - first).to_std().ok()
+}
 
-    // Process the html into only the devblog links.
-    let devblogs = Html::parse_document(&html)
-        .select(&selector)
-        .filter_map(|e| e.value().attr("href").map(|s| s.to_string() + "\n"))
-        .collect::<String>();
+// A check can take a while with retries, so the daemon is only considered stuck when it hasn't
+// fetched for two intervals.
+#[cfg(feature = "http-server")]
+fn serve(addr: &str, config: &Config, interval: Duration, requested: Arc<Notify>) {
+    let addr: SocketAddr = addr.parse().expect("Unable to parse the HTTP address.");
+    let watched = config
+        .projects()
+        .iter()
+        .filter_map(sources::changelog_url)
+        .filter_map(|url| server::gitlab_path(&url))
+        .collect();
+    let triggers = server::Triggers {
+        check_token: config.daemon.check_token.clone(),
+        webhook_secret: config.daemon.gitlab_webhook_secret.clone(),
+        watched,
+        requested,
+    };
+    tokio::spawn(server::serve(addr, interval * 2, triggers));
+}
 
-    fs::write(DEVBLOGS_PATH, &devblogs).expect("Unable to write to file.");
-    Ok(devblogs)
+#[cfg(not(feature = "http-server"))]
+fn serve(_: &str, _: &Config, _: Duration, _: Arc<Notify>) {
+    tracing::warn!(
+        "Built without the `http-server` feature, not serving metrics, health or checks"
+    );
 }
 
-async fn read_devblogs() -> io::Result<String> {
-    fs::read_to_string(DEVBLOGS_PATH)
+// The announcements of the past posts or releases from `from` up to and including `to` (or the
+// latest one), oldest first.
+async fn backfill(
+    config: &Config,
+    project: &ProjectConfig,
+    source: BackfillSource,
+    from: &str,
+    to: Option<&str>,
+) -> reqwest::Result<Vec<Announcement>> {
+    match source {
+        BackfillSource::Devblogs => backfill_devblogs(config, project, from, to).await,
+        BackfillSource::Releases => backfill_releases(config, project, from, to).await,
+    }
 }
 
-struct Handler {
-    message: String,
+// All devblogs are announced at once.
+async fn backfill_devblogs(
+    config: &Config,
+    project: &ProjectConfig,
+    from: &str,
+    to: Option<&str>,
+) -> reqwest::Result<Vec<Announcement>> {
+    let Some(feed_url) = sources::feed_url(project) else {
+        tracing::error!(project = project.name(), "The project has no blog");
+        return Ok(vec![]);
+    };
+    let from = devblog::path(from);
+    let to = to.map(devblog::path);
+
+    let paths = devblog::crawl(&config.retry, &feed_url, Some(&from)).await?;
+    let Some(end) = paths.iter().position(|s| s == &from) else {
+        tracing::error!(devblog = from, "Unable to find the devblog");
+        return Ok(vec![]);
+    };
+
+    let start = match &to {
+        Some(to) => match paths.iter().position(|s| s == to) {
+            Some(i) if i <= end => i,
+            _ => {
+                tracing::error!(devblog = to, before = from, "Unable to find the devblog");
+                return Ok(vec![]);
+            }
+        },
+        None => 0,
+    };
+
+    // Oldest first, like the devblogs that are found by polling.
+    let mut updates = vec![];
+    for path in paths[start..=end].iter().rev() {
+        updates.push(Update::Devblog(
+            devblog::fetch(&config.retry, &feed_url, path).await?,
+        ));
+    }
+
+    Ok(Announcement::new(updates, &config.announcement, project)
+        .into_iter()
+        .collect())
 }
 
-#[serenity::async_trait]
-impl EventHandler for Handler {
-    async fn ready(&self, context: Context, _: Ready) {
-        for guild_id in context.cache.guilds() {
-            for (_, channel) in guild_id.channels(&context.http).await.unwrap() {
-                if channel.name == "veloren-updates"
-                    && channel.say(&context.http, &self.message).await.is_err()
-                {
-                    println!(
-                        "Channel {} in guild {} cannot be written to.",
-                        channel.id, channel.guild_id
-                    );
-                }
+// Every release is announced on its own, with the entries of its notes.
+async fn backfill_releases(
+    config: &Config,
+    project: &ProjectConfig,
+    from: &str,
+    to: Option<&str>,
+) -> reqwest::Result<Vec<Announcement>> {
+    let Some(url) = sources::changelog_url(project) else {
+        tracing::error!(project = project.name(), "The project has no changelog");
+        return Ok(vec![]);
+    };
+    let releases = changelog::all_releases(&changelog::fetch(&config.retry, &url).await?);
+    let Some(releases) = release_range(&releases, from, to) else {
+        return Ok(vec![]);
+    };
+
+    let mut announcements = vec![];
+    for release in releases.iter().rev() {
+        let mut updates = vec![Update::Release(release.clone())];
+        updates.extend(changelog::sections(&release.notes));
+        announcements.extend(Announcement::new(updates, &config.announcement, project));
+    }
+
+    Ok(announcements)
+}
+
+// Print the changes of the versions after `from` up to and including `to` (or the latest version),
+// with the sections of all versions merged.
+async fn diff_versions(
+    config: &Config,
+    project: &ProjectConfig,
+    from: &str,
+    to: Option<&str>,
+    format: Format,
+) -> reqwest::Result<()> {
+    let Some(url) = sources::changelog_url(project) else {
+        tracing::error!(project = project.name(), "The project has no changelog");
+        return Ok(());
+    };
+    let releases = changelog::all_releases(&changelog::fetch(&config.retry, &url).await?);
+    let Some(releases) = release_range(&releases, from, to) else {
+        return Ok(());
+    };
+
+    // Oldest first, like the entries of an announcement.
+    let updates = releases[..releases.len() - 1]
+        .iter()
+        .rev()
+        .flat_map(|r| changelog::sections(&r.notes))
+        .collect();
+    let announcement = Announcement::new(updates, &config.announcement, project);
+
+    match format {
+        Format::Markdown => {
+            if let Some(a) = announcement {
+                println!("{}", a.changes.join("\n"));
             }
         }
+        // The versioned sections don't change anymore, so the commit doesn't matter.
+        Format::Json => println!("{}", json::render(announcement.as_ref(), None)),
+    }
+
+    Ok(())
+}
+
+// Print the announcement of the next check. The state is only read, so the next check still finds
+// the same changes.
+async fn fetch(config: &Config, state_dir: &Path, format: Format) -> reqwest::Result<()> {
+    for project in config.projects() {
+        let state = project_state(&project, state_dir);
+        let mut updates = vec![];
+        for source in sources::from_config(config, &project) {
+            updates.extend(source.poll(&state).await?);
+        }
+        let announcement = Announcement::new(updates, &config.announcement, &project);
 
-        // Close the shards and consequently the bot. See note by SHARD_MANAGER for more
-        // information about this unsafe block.
-        unsafe {
-            if let Some(sm) = &SHARD_MANAGER {
-                sm.lock().await.shutdown_all().await;
+        match format {
+            Format::Markdown => {
+                if let Some(a) = announcement {
+                    println!("{}", a.message());
+                }
+            }
+            // Only the commit of Veloren's changelog is known.
+            Format::Json => {
+                let commit = match project.is_veloren() {
+                    true => match changelog::commit(&config.retry).await {
+                        Ok(c) => Some(c),
+                        Err(e) => {
+                            tracing::warn!(error = %e, "Unable to find the commit of the changelog");
+                            None
+                        }
+                    },
+                    false => None,
+                };
+                println!("{}", json::render(announcement.as_ref(), commit.as_deref()));
             }
         }
     }
+
+    Ok(())
+}
+
+// The releases from `from` up to and including `to` (or the latest release), newest first, or
+// nothing if either isn't a version or can't be found. Versions may be given with a leading `v`.
+fn release_range<'a>(
+    releases: &'a [changelog::Release],
+    from: &str,
+    to: Option<&str>,
+) -> Option<&'a [changelog::Release]> {
+    let position = |version: &str| {
+        let Some(version) = changelog::parse_version(version) else {
+            tracing::error!(version, "Not a version, like 0.15.0");
+            return Err(());
+        };
+        Ok(releases
+            .iter()
+            .position(|r| changelog::parse_version(&r.version).as_ref() == Some(&version)))
+    };
+
+    let Ok(Some(end)) = position(from) else {
+        tracing::error!(version = from, "Unable to find the version");
+        return None;
+    };
+
+    let start = match to {
+        Some(to) => match position(to) {
+            Ok(Some(i)) if i <= end => i,
+            _ => {
+                tracing::error!(version = to, before = from, "Unable to find the version");
+                return None;
+            }
+        },
+        None => 0,
+    };
+
+    Some(&releases[start..=end])
+}
+
+// Correct the announcements with entries that were removed upstream, on every platform that can.
+// The corrections aren't retried, so failing to correct an announcement is only logged.
+async fn correct(config: &Config, entries: &[String], discord: Discord, state_dir: &Path) {
+    if entries.is_empty() {
+        return;
+    }
+    for sink in sinks::from_config(config, discord, state_dir) {
+        let span = tracing::info_span!("correct", sink = sink.name());
+        let delivery = sink.correct(entries).instrument(span).await;
+        METRICS.published(sink.name(), &delivery);
+    }
+}
+
+// Post the announcement to every configured platform.
+async fn announce(
+    config: &Config,
+    announcement: Announcement,
+    discord: Discord,
+    state_dir: &Path,
+) -> Delivery {
+    let mut delivery = Delivery::default();
+    for sink in sinks::from_config(config, discord, state_dir) {
+        let span = tracing::info_span!("publish", sink = sink.name());
+        let sink_delivery = sink.publish(&announcement).instrument(span).await;
+        METRICS.published(sink.name(), &sink_delivery);
+        delivery.merge(sink_delivery);
+    }
+
+    for target in &delivery.failed {
+        tracing::warn!(to = target, "Unable to deliver the announcement");
+    }
+
+    for target in &delivery.queued {
+        tracing::warn!(
+            to = target,
+            "Unable to deliver the announcement, retrying on the next run"
+        );
+    }
+
+    delivery
 }
@@ -0,0 +1,202 @@
+// Without the HTTP server, the metrics are collected but never read.
+#![cfg_attr(not(feature = "http-server"), allow(dead_code))]
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::sinks::Delivery;
+use crate::sources::Update;
+
+// Counters for the `/metrics` and `/healthz` endpoints of the daemon. They are collected in every
+// mode, but only ever exported by the daemon.
+pub static METRICS: Metrics = Metrics::new();
+
+pub struct Metrics {
+    // Keyed by source and whether the fetch succeeded.
+    fetches: Mutex<BTreeMap<(&'static str, bool), u64>>,
+    // Number of new entries found by the last poll of each source.
+    diff_entries: Mutex<BTreeMap<&'static str, u64>>,
+    posts: Mutex<BTreeMap<&'static str, u64>>,
+    // Keyed by sink and target, which for Discord is a channel in a guild.
+    post_failures: Mutex<BTreeMap<(&'static str, String), u64>>,
+    // Number of times a request to Discord had to wait for a rate limit.
+    rate_limits: AtomicU64,
+    last_success: AtomicI64,
+    last_fetch: AtomicI64,
+    last_post: AtomicI64,
+    // `None` until the bot has connected to Discord for the first time.
+    gateway_connected: Mutex<Option<bool>>,
+}
+
+impl Metrics {
+    const fn new() -> Metrics {
+        Metrics {
+            fetches: Mutex::new(BTreeMap::new()),
+            diff_entries: Mutex::new(BTreeMap::new()),
+            posts: Mutex::new(BTreeMap::new()),
+            post_failures: Mutex::new(BTreeMap::new()),
+            rate_limits: AtomicU64::new(0),
+            last_success: AtomicI64::new(0),
+            last_fetch: AtomicI64::new(0),
+            last_post: AtomicI64::new(0),
+            gateway_connected: Mutex::new(None),
+        }
+    }
+
+    pub fn polled<E>(&self, source: &'static str, result: &Result<Vec<Update>, E>) {
+        self.last_fetch.store(now(), Ordering::Relaxed);
+        *self
+            .fetches
+            .lock()
+            .unwrap()
+            .entry((source, result.is_ok()))
+            .or_default() += 1;
+
+        if let Ok(updates) = result {
+            let entries = updates.iter().map(|u| u.size() as u64).sum();
+            self.diff_entries.lock().unwrap().insert(source, entries);
+        }
+    }
+
+    pub fn published(&self, sink: &'static str, delivery: &Delivery) {
+        *self.posts.lock().unwrap().entry(sink).or_default() += delivery.sent as u64;
+        if delivery.sent > 0 {
+            self.last_post.store(now(), Ordering::Relaxed);
+        }
+
+        let mut post_failures = self.post_failures.lock().unwrap();
+        for target in delivery.failed.iter().chain(&delivery.queued) {
+            *post_failures.entry((sink, target.clone())).or_default() += 1;
+        }
+    }
+
+    // Called once a run has been delivered everywhere, or had nothing to deliver.
+    pub fn succeeded(&self) {
+        self.last_success.store(now(), Ordering::Relaxed);
+    }
+
+    pub fn rate_limited(&self) {
+        self.rate_limits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn gateway_connected(&self, connected: bool) {
+        *self.gateway_connected.lock().unwrap() = Some(connected);
+    }
+
+    pub fn health(&self) -> Health {
+        Health {
+            last_fetch: self.last_fetch.load(Ordering::Relaxed),
+            last_post: self.last_post.load(Ordering::Relaxed),
+            gateway_connected: *self.gateway_connected.lock().unwrap(),
+        }
+    }
+
+    // Render the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "fetches_total",
+            "counter",
+            "Number of times a source was polled.",
+        );
+        for ((source, ok), n) in self.fetches.lock().unwrap().iter() {
+            let result = if *ok { "ok" } else { "error" };
+            let labels = [("source", *source), ("result", result)];
+            sample(&mut out, "fetches_total", &labels, *n);
+        }
+
+        header(
+            &mut out,
+            "diff_entries",
+            "gauge",
+            "New entries found by the last poll of a source.",
+        );
+        for (source, n) in self.diff_entries.lock().unwrap().iter() {
+            sample(&mut out, "diff_entries", &[("source", source)], *n);
+        }
+
+        header(
+            &mut out,
+            "posts_total",
+            "counter",
+            "Number of targets an announcement was posted to.",
+        );
+        for (sink, n) in self.posts.lock().unwrap().iter() {
+            sample(&mut out, "posts_total", &[("sink", sink)], *n);
+        }
+
+        header(
+            &mut out,
+            "post_failures_total",
+            "counter",
+            "Number of failed posts to a target.",
+        );
+        for ((sink, target), n) in self.post_failures.lock().unwrap().iter() {
+            let labels = [("sink", *sink), ("target", target.as_str())];
+            sample(&mut out, "post_failures_total", &labels, *n);
+        }
+
+        header(
+            &mut out,
+            "rate_limits_total",
+            "counter",
+            "Number of times a request to Discord waited for a rate limit.",
+        );
+        let rate_limits = self.rate_limits.load(Ordering::Relaxed);
+        let _ = writeln!(out, "veloren_changelog_rate_limits_total {rate_limits}");
+
+        header(
+            &mut out,
+            "last_success_timestamp_seconds",
+            "gauge",
+            "Unix time of the last run that was delivered everywhere.",
+        );
+        let last_success = self.last_success.load(Ordering::Relaxed);
+        let _ = writeln!(
+            out,
+            "veloren_changelog_last_success_timestamp_seconds {last_success}"
+        );
+
+        out
+    }
+}
+
+// Unix timestamps of the last fetch and post, zero if there hasn't been one yet.
+#[derive(Serialize)]
+pub struct Health {
+    pub last_fetch: i64,
+    pub last_post: i64,
+    pub gateway_connected: Option<bool>,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP veloren_changelog_{name} {help}");
+    let _ = writeln!(out, "# TYPE veloren_changelog_{name} {kind}");
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: u64) {
+    let labels = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", escape(v)))
+        .collect::<Vec<_>>()
+        .join(",");
+    let _ = writeln!(out, "veloren_changelog_{name}{{{labels}}} {value}");
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
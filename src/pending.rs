@@ -0,0 +1,96 @@
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::NaiveTime;
+use chrono::Utc;
+use chrono::Weekday;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::BatchConfig;
+use crate::config::DigestConfig;
+use crate::sources::Update;
+use crate::state::State;
+
+const PENDING_PATH: &str = "PENDING.json";
+
+// Updates that have been found but not announced yet, because they are held back for the digest or
+// until enough of them have been found.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Pending {
+    // Unix timestamp of when the oldest update was found.
+    since: i64,
+    updates: Vec<Update>,
+}
+
+impl Pending {
+    pub fn load(state: &State) -> Pending {
+        match state.read(PENDING_PATH) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+                tracing::error!(error = %e, "Unable to parse the pending updates, dropping them");
+                Pending::default()
+            }),
+            Err(_) => Pending::default(),
+        }
+    }
+
+    pub fn add(&mut self, updates: Vec<Update>, now: DateTime<Utc>) {
+        if self.updates.is_empty() {
+            self.since = now.timestamp();
+        }
+        self.updates.extend(updates);
+    }
+
+    // Whether the updates should be announced now. With a digest, they are once the digest time
+    // has passed since the oldest update was found. With a batch, they are once there are enough
+    // of them or the oldest is old enough. Otherwise, they always are.
+    pub fn due(
+        &self,
+        digest: Option<&DigestConfig>,
+        batch: Option<&BatchConfig>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        if let Some(config) = digest {
+            if self.updates.is_empty() || self.since >= last_digest(config, now).timestamp() {
+                return false;
+            }
+        }
+
+        if let Some(config) = batch {
+            let size = self.updates.iter().map(|u| u.size()).sum::<usize>();
+            let age = now.timestamp() - self.since;
+            if size < config.min_entries && age < config.max_age_days as i64 * 86400 {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Take the updates to announce them, leaving nothing pending.
+    pub fn take(&mut self) -> Vec<Update> {
+        std::mem::take(&mut self.updates)
+    }
+
+    pub fn save(&self, state: &State) {
+        state.write(PENDING_PATH, &serde_json::to_string(self).unwrap());
+    }
+}
+
+// The latest time a digest was scheduled for, at or before now.
+fn last_digest(config: &DigestConfig, now: DateTime<Utc>) -> DateTime<Utc> {
+    // Both have already been checked when loading the config.
+    let weekday = config.weekday.parse::<Weekday>().unwrap();
+    let time = NaiveTime::parse_from_str(&config.time, "%H:%M").unwrap();
+
+    let days_since =
+        (now.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+    let digest = (now.date_naive() - Duration::days(days_since as i64))
+        .and_time(time)
+        .and_utc();
+
+    match digest > now {
+        true => digest - Duration::days(7),
+        false => digest,
+    }
+}
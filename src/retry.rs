@@ -0,0 +1,77 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::config::RetryConfig;
+
+// Errors that might go away when trying again, as opposed to e.g. missing permissions.
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+}
+
+impl Transient for reqwest::Error {
+    fn is_transient(&self) -> bool {
+        match self.status() {
+            Some(s) => s.is_server_error() || s.as_u16() == 429,
+            None => self.is_timeout() || self.is_connect() || self.is_request() || self.is_body(),
+        }
+    }
+}
+
+impl Transient for serenity::Error {
+    fn is_transient(&self) -> bool {
+        match self {
+            serenity::Error::Http(e) => match e.as_ref() {
+                serenity::http::HttpError::UnsuccessfulRequest(r) => {
+                    r.status_code.is_server_error() || r.status_code.as_u16() == 429
+                }
+                serenity::http::HttpError::Request(e) => e.is_transient(),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+// Run `f` until it succeeds, it fails with an error that isn't transient, or the configured number
+// of attempts is reached. The delay between attempts grows exponentially, with random jitter so
+// that several retrying clients don't end up in lockstep.
+pub async fn retry<T, E, F, Fut>(config: &RetryConfig, mut f: F) -> Result<T, E>
+where
+    E: Transient + Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Ok(t) => return Ok(t),
+            Err(e) if attempt < config.attempts && e.is_transient() => {
+                let delay = backoff(config, attempt);
+                tracing::warn!(
+                    attempt,
+                    attempts = config.attempts,
+                    ?delay,
+                    error = %e,
+                    "Request failed, retrying"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// The delay after the given attempt: a random duration between half and all of the exponentially
+// growing base delay.
+fn backoff(config: &RetryConfig, attempt: u32) -> Duration {
+    let delay = config
+        .base_delay_ms
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(config.max_delay_ms);
+    let delay = rand::thread_rng().gen_range(delay / 2..=delay);
+    Duration::from_millis(delay)
+}
@@ -0,0 +1,222 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::service::make_service_fn;
+use hyper::service::service_fn;
+use hyper::Body;
+use hyper::Method;
+use hyper::Request;
+use hyper::Response;
+use hyper::Server;
+use hyper::StatusCode;
+use serde::Deserialize;
+use tokio::sync::Notify;
+
+use crate::metrics::METRICS;
+
+// What may ask the daemon to check right away, through `requested`. Without a token or secret, the
+// endpoint doesn't exist, so that anyone who can reach the metrics can't make the daemon post.
+pub struct Triggers {
+    pub check_token: Option<String>,
+    pub webhook_secret: Option<String>,
+    // The branches and paths of the watched changelogs in their repositories on GitLab.
+    pub watched: Vec<(String, String)>,
+    pub requested: Arc<Notify>,
+}
+
+#[derive(Deserialize)]
+struct Push {
+    #[serde(rename = "ref")]
+    reference: String,
+    #[serde(default)]
+    commits: Vec<Commit>,
+    #[serde(default)]
+    total_commits_count: usize,
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    #[serde(default)]
+    added: Vec<String>,
+    #[serde(default)]
+    modified: Vec<String>,
+    #[serde(default)]
+    removed: Vec<String>,
+}
+
+// Serve the daemon's metrics and health over HTTP until the process exits. The daemon is reported
+// unhealthy once it has gone `max_age` without fetching, or while it is disconnected from Discord.
+pub async fn serve(addr: SocketAddr, max_age: Duration, triggers: Triggers) {
+    let started = chrono::Utc::now().timestamp();
+    let triggers = Arc::new(triggers);
+    let make_service = make_service_fn(move |_| {
+        let triggers = triggers.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |r| {
+                handle(r, started, max_age, triggers.clone())
+            }))
+        }
+    });
+
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder.serve(make_service),
+        Err(e) => {
+            tracing::error!(%addr, error = %e, "Unable to start the HTTP server");
+            return;
+        }
+    };
+
+    tracing::info!(%addr, "Serving metrics and health");
+    if let Err(e) = server.await {
+        tracing::error!(error = %e, "HTTP server stopped");
+    }
+}
+
+async fn handle(
+    request: Request<Body>,
+    started: i64,
+    max_age: Duration,
+    triggers: Arc<Triggers>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match request.uri().path() {
+        "/check" if triggers.check_token.is_some() => {
+            let token = request
+                .headers()
+                .get("Authorization")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "));
+            let authorized = secret_matches(token, triggers.check_token.as_deref());
+            let status = match (request.method(), authorized) {
+                (&Method::POST, true) => {
+                    // Requests while a check is running are merged into a single check after it.
+                    triggers.requested.notify_one();
+                    StatusCode::ACCEPTED
+                }
+                (&Method::POST, false) => StatusCode::UNAUTHORIZED,
+                _ => StatusCode::METHOD_NOT_ALLOWED,
+            };
+
+            Response::builder().status(status).body(Body::empty())
+        }
+        "/webhook/gitlab" if triggers.webhook_secret.is_some() => {
+            let status = webhook(request, &triggers).await;
+            Response::builder().status(status).body(Body::empty())
+        }
+        "/healthz" => {
+            let health = METRICS.health();
+            let age = chrono::Utc::now().timestamp() - health.last_fetch.max(started);
+            let healthy =
+                age <= max_age.as_secs() as i64 && health.gateway_connected != Some(false);
+            let status = match healthy {
+                true => StatusCode::OK,
+                false => StatusCode::SERVICE_UNAVAILABLE,
+            };
+
+            Response::builder()
+                .status(status)
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::to_string(&health).unwrap()))
+        }
+        "/metrics" => Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from(METRICS.render())),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+
+    Ok(response.expect("Unable to build the response."))
+}
+
+// Whether the given secret is the expected one, comparing every byte so that the time taken doesn't
+// tell how much of it was right. Only the length can be told apart.
+fn secret_matches(given: Option<&str>, expected: Option<&str>) -> bool {
+    let (Some(given), Some(expected)) = (given, expected) else {
+        return false;
+    };
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |d, (a, b)| d | (a ^ b))
+            == 0
+}
+
+// The branch and path of a changelog on GitLab from the URL of its raw file, e.g. `weekly` and
+// `CHANGELOG.md` for Veloren's.
+pub fn gitlab_path(url: &str) -> Option<(String, String)> {
+    let url = url.split(['?', '#']).next()?;
+    let (_, file) = url.split_once("/-/raw/")?;
+    let (branch, path) = file.split_once('/')?;
+    Some((branch.to_string(), path.to_string()))
+}
+
+// Check right away for a push to the branch of a watched changelog that changed it. Other events
+// and pushes are acknowledged and ignored, so that GitLab doesn't disable the webhook.
+async fn webhook(request: Request<Body>, triggers: &Triggers) -> StatusCode {
+    if request.method() != Method::POST {
+        return StatusCode::METHOD_NOT_ALLOWED;
+    }
+    let header = |name| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    // The body is only read once the secret is known to be right.
+    if !secret_matches(
+        header("X-Gitlab-Token").as_deref(),
+        triggers.webhook_secret.as_deref(),
+    ) {
+        return StatusCode::UNAUTHORIZED;
+    }
+    if header("X-Gitlab-Event").as_deref() != Some("Push Hook") {
+        return StatusCode::NO_CONTENT;
+    }
+
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!(error = %e, "Unable to read the GitLab webhook");
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+    let push: Push = match serde_json::from_slice(&body) {
+        Ok(push) => push,
+        Err(e) => {
+            tracing::warn!(error = %e, "Unable to parse the GitLab push");
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    // GitLab only lists the first 20 commits of a push, so a longer push may change the changelog
+    // in one of those left out.
+    let truncated = push.total_commits_count > push.commits.len();
+    let changed = triggers.watched.iter().any(|(branch, path)| {
+        push.reference == format!("refs/heads/{}", branch)
+            && (truncated
+                || push.commits.iter().any(|c| {
+                    [&c.added, &c.modified, &c.removed]
+                        .iter()
+                        .any(|files| files.contains(path))
+                }))
+    });
+
+    match changed {
+        true => {
+            tracing::info!(reference = push.reference, "Changelog was pushed to");
+            triggers.requested.notify_one();
+            StatusCode::ACCEPTED
+        }
+        false => {
+            tracing::debug!(
+                reference = push.reference,
+                "Ignoring a push without the changelog"
+            );
+            StatusCode::NO_CONTENT
+        }
+    }
+}
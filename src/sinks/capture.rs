@@ -0,0 +1,59 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::OnceLock;
+
+use async_trait::async_trait;
+
+use crate::announcement::Announcement;
+use crate::json;
+use crate::sinks::Delivery;
+use crate::sinks::Sink;
+
+static CAPTURE_PATH: OnceLock<String> = OnceLock::new();
+
+// Stands in for every other sink, appending the rendered announcements to a file instead of
+// posting them, so that a replayed run can be compared with its expected output.
+pub struct CaptureSink {
+    path: String,
+}
+
+// Capture the announcements of this run to the file at `path`.
+pub fn capture(path: &str) {
+    CAPTURE_PATH.get_or_init(|| path.to_string());
+}
+
+// The capturing sink, if announcements are captured.
+pub fn from_path() -> Option<CaptureSink> {
+    CAPTURE_PATH.get().map(|p| CaptureSink { path: p.clone() })
+}
+
+#[async_trait]
+impl Sink for CaptureSink {
+    fn name(&self) -> &'static str {
+        "capture"
+    }
+
+    // The message as it is posted to Discord, followed by the announcement as it is printed by
+    // `fetch --format json`.
+    async fn publish(&self, announcement: &Announcement) -> Delivery {
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| {
+                writeln!(
+                    file,
+                    "{}\n\n{}\n",
+                    announcement.message(),
+                    json::render(Some(announcement), None)
+                )
+            });
+        if let Err(e) = &result {
+            tracing::warn!(file = self.path, error = %e, "Unable to capture the announcement");
+        }
+
+        let mut delivery = Delivery::default();
+        delivery.record(format!("Capture file {}", self.path), &result);
+        delivery
+    }
+}
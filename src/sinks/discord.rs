@@ -0,0 +1,1727 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future;
+use serde_json::json;
+use serde_json::Value;
+use serenity::builder::CreateComponents;
+use serenity::builder::CreateEmbed;
+use serenity::cache::Cache;
+use serenity::client::bridge::gateway::event::ShardStageUpdateEvent;
+use serenity::client::bridge::gateway::ShardManager;
+use serenity::client::ClientBuilder;
+use serenity::gateway::ConnectionStage;
+use serenity::http::ratelimiting::RatelimitInfo;
+use serenity::http::Http;
+use serenity::http::HttpBuilder;
+use serenity::http::HttpError;
+use serenity::json::hashmap_to_json_map;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::application::interaction::Interaction;
+use serenity::model::channel::AttachmentType;
+use serenity::model::channel::Channel;
+use serenity::model::channel::ChannelType;
+use serenity::model::channel::GuildChannel;
+use serenity::model::channel::Message;
+use serenity::model::gateway::GatewayIntents;
+use serenity::model::gateway::Ready;
+use serenity::model::id::ChannelId;
+use serenity::model::id::GuildId;
+use serenity::model::id::MessageId;
+use serenity::model::id::RoleId;
+use serenity::model::id::UserId;
+use serenity::model::mention::Mentionable;
+use serenity::model::permissions::Permissions;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use tokio::sync::oneshot;
+use tokio::sync::Notify;
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+
+use crate::announcement::Announcement;
+use crate::config::Corrections;
+use crate::config::DiscordConfig;
+use crate::config::GuildConfig;
+use crate::config::LinkConfig;
+use crate::config::RetryConfig;
+use crate::config::StagingConfig;
+use crate::guilds::Delivered;
+use crate::guilds::Guilds;
+use crate::guilds::Posted;
+use crate::guilds::Style;
+use crate::guilds::Undelivered;
+use crate::http;
+use crate::locale;
+use crate::metrics::METRICS;
+use crate::retry::retry;
+use crate::sinks::Delivery;
+use crate::sinks::Sink;
+use crate::sources::artifacts::Artifacts;
+use crate::sources::changelog;
+use crate::sources::changelog::Release;
+use crate::sources::devblog::Devblog;
+use crate::sources::twitch::Stream;
+use crate::sources::Update;
+use crate::translate;
+
+use pages::Page;
+use pages::MAX_PAGED;
+use pages::MAX_PAGE_LENGTH;
+
+mod commands;
+mod events;
+mod markdown;
+mod pages;
+mod roles;
+mod staging;
+
+// Limits of Discord: embeds and characters per message, and characters per thread name.
+const MAX_EMBEDS: usize = 10;
+const MAX_LENGTH: usize = 2000;
+const MAX_THREAD_NAME_LENGTH: usize = 100;
+
+// Announcements and release notes that need more messages than this are attached as files.
+const MAX_INLINE_MESSAGES: usize = 5;
+
+const TWITCH_COLOUR: u32 = 0x9146ff;
+
+// Buttons below every announcement about Veloren unless others are configured, as the key of their
+// label and their URL.
+const DEFAULT_LINKS: &[(&str, &str)] = &[
+    ("link-changelog", changelog::CHANGELOG_PAGE_URL),
+    ("link-launcher", "https://veloren.net/download/"),
+    ("link-devblog", "https://veloren.net/blog/"),
+];
+
+// Seconds after which an announcement that couldn't be delivered to a guild is dropped.
+const MAX_UNDELIVERED_AGE: i64 = 7 * 24 * 3600;
+
+// How long posted announcements stay in the ledger, which covers every retry of an announcement
+// since those are dropped after the same time.
+const MAX_LEDGER_AGE: i64 = MAX_UNDELIVERED_AGE;
+
+// Number of guilds posted to at once, unless configured otherwise.
+const DEFAULT_CONCURRENCY: usize = 8;
+
+pub struct DiscordSink {
+    config: DiscordConfig,
+    retry: RetryConfig,
+    discord: Discord,
+}
+
+// How the sink reaches Discord. The daemon stays connected between announcements, otherwise the bot
+// connects with the token for every announcement.
+#[derive(Clone)]
+pub enum Discord {
+    Token(String, Arc<Guilds>),
+    Gateway(Arc<Gateway>),
+}
+
+impl Discord {
+    pub fn guilds(&self) -> Arc<Guilds> {
+        match self {
+            Discord::Token(_, guilds) => guilds.clone(),
+            Discord::Gateway(gateway) => gateway.guilds.clone(),
+        }
+    }
+}
+
+impl DiscordSink {
+    pub fn new(config: DiscordConfig, retry: RetryConfig, discord: Discord) -> DiscordSink {
+        DiscordSink {
+            config,
+            retry,
+            discord,
+        }
+    }
+
+    // Limits how many guilds are posted to at once. Serenity already waits out the rate limits of
+    // every route, so the limit only keeps a bot in many guilds from piling all of its requests
+    // onto the same limits at once.
+    fn semaphore(&self) -> Semaphore {
+        Semaphore::new(
+            self.config
+                .concurrency
+                .unwrap_or(DEFAULT_CONCURRENCY)
+                .max(1),
+        )
+    }
+
+    // The persistent connection of the daemon, or a new one.
+    async fn connect(&self) -> Option<Arc<Gateway>> {
+        match &self.discord {
+            Discord::Gateway(g) => Some(g.clone()),
+            Discord::Token(token, guilds) => {
+                let gateway = Gateway::connect(
+                    token,
+                    guilds.clone(),
+                    self.config.clone(),
+                    self.retry.clone(),
+                    false,
+                    None,
+                )
+                .await;
+                if gateway.is_none() {
+                    tracing::error!("Bot stopped before posting");
+                }
+                gateway.map(Arc::new)
+            }
+        }
+    }
+
+    // Close the connection, unless it's the persistent one.
+    async fn disconnect(&self, gateway: Arc<Gateway>) {
+        if let Discord::Token(..) = self.discord {
+            gateway.shutdown().await;
+        }
+    }
+
+    // Post the announcement to the target channels of the guild, or only to the given channel.
+    // Whatever can't be posted to is queued with the time the announcement was first queued, to be
+    // posted on the next run.
+    async fn post(
+        &self,
+        gateway: &Gateway,
+        announcement: &Announcement,
+        guild_id: GuildId,
+        channel: Option<ChannelId>,
+        queued_at: i64,
+    ) -> Delivery {
+        let http = &gateway.http;
+        let guilds = &gateway.guilds;
+        let content = self.content(guilds, guild_id, announcement);
+
+        let queue = |delivery: &mut Delivery, target: String, channel: Option<u64>| {
+            guilds.update(guild_id.0, |g| {
+                g.undelivered.push(Undelivered {
+                    announcement: announcement.clone(),
+                    channel,
+                    queued_at,
+                })
+            });
+            delivery.queued.push(target);
+        };
+
+        let mut delivery = Delivery::default();
+        let channels = match channel {
+            Some(id) => match id.to_channel(http).await {
+                Ok(Channel::Guild(c)) => vec![c],
+                Ok(_) => vec![],
+                Err(e) if unknown(&e) => {
+                    tracing::warn!(channel = %id, "Channel is gone, dropping the announcement");
+                    vec![]
+                }
+                Err(e) => {
+                    tracing::warn!(channel = %id, error = %e, "Unable to get the channel");
+                    let target = format!("Channel {} in guild {}", id, guild_id);
+                    queue(&mut delivery, target, Some(id.0));
+                    return delivery;
+                }
+            },
+            None => match self
+                .channels(gateway, guild_id, &announcement.channels)
+                .await
+            {
+                Ok(c) => c,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Unable to get the channels of the guild");
+                    queue(&mut delivery, format!("Guild {}", guild_id), None);
+                    return delivery;
+                }
+            },
+        };
+
+        let hash = announcement.hash();
+        for channel in channels {
+            let target = format!("Channel {} in guild {}", channel.id, channel.guild_id);
+
+            let ledger = guilds.get(guild_id.0).ledger;
+            let posted = ledger
+                .iter()
+                .find(|d| d.hash == hash && d.channel == channel.id.0);
+            if let Some(posted) = posted {
+                tracing::info!(
+                    channel = %channel.id,
+                    messages = ?posted.messages,
+                    "Announcement was already posted to the channel"
+                );
+                delivery.sent += 1;
+                continue;
+            }
+
+            let missing = missing_permissions(gateway, announcement, &content, &channel);
+            if !missing.is_empty() {
+                tracing::warn!(
+                    channel = %channel.id,
+                    missing = ?missing.get_permission_names(),
+                    "Bot lacks permissions in the channel"
+                );
+                queue(&mut delivery, target, Some(channel.id.0));
+                continue;
+            }
+
+            let result = match self.edit(http, guilds, announcement, &channel).await {
+                Some(message) => Ok(vec![message]),
+                None => {
+                    let result = self.send(http, announcement, &content, &channel).await;
+                    if let Ok(messages) = &result {
+                        self.posted(http, guilds, announcement, &channel, &messages[0])
+                            .await;
+                        if content.pages.len() > 1 {
+                            remember_pages(guilds, guild_id, &messages[0], content.pages.clone());
+                        }
+                    }
+                    result
+                }
+            };
+
+            match &result {
+                Ok(messages) => {
+                    tracing::debug!(channel = %channel.id, messages = messages.len(), "Posted")
+                }
+                Err(e) => {
+                    tracing::warn!(channel = %channel.id, error = %e, "Channel cannot be written to")
+                }
+            }
+
+            match result {
+                Ok(messages) => {
+                    record(guilds, &channel, announcement, &messages);
+                    delivery.sent += 1;
+                }
+                Err(_) => queue(&mut delivery, target, Some(channel.id.0)),
+            }
+        }
+
+        delivery
+    }
+
+    // The announcement in the given language, with the entries machine-translated if enabled. The
+    // entries stay in English if they can't be translated.
+    async fn localize(&self, announcement: &Announcement, language: &str) -> Announcement {
+        let config = self.config.languages.get(language);
+        if config.is_none() && !locale::exists(language) {
+            tracing::warn!(language, "Language has no translations");
+            return announcement.clone();
+        }
+        let config = config.cloned().unwrap_or_default();
+
+        let entries = match (&self.config.translation, config.translate) {
+            (Some(translation), true) => {
+                translate::translate(translation, &announcement.entries(), language).await
+            }
+            (None, true) => {
+                tracing::warn!(
+                    language,
+                    "Translation is enabled without a translation service"
+                );
+                None
+            }
+            _ => None,
+        };
+
+        let heading = match &announcement.project {
+            Some(project) => locale::text(Some(language), "project-title", &[("project", project)]),
+            None => config
+                .title
+                .unwrap_or_else(|| locale::title(Some(language))),
+        };
+        let mut titles = locale::sections(Some(language));
+        titles.extend(config.sections);
+
+        announcement.translated(language, heading, titles, entries)
+    }
+
+    // The channels of the project that are in the guild, the channels configured for the guild, or
+    // the global channels that are in the guild. Without any configured channels, every channel
+    // named "veloren-updates".
+    async fn channels(
+        &self,
+        gateway: &Gateway,
+        guild_id: GuildId,
+        project: &[u64],
+    ) -> serenity::Result<Vec<GuildChannel>> {
+        let ids = match self.config.guilds.get(&guild_id.to_string()) {
+            _ if !project.is_empty() => project,
+            Some(guild) if !guild.channels.is_empty() => &guild.channels,
+            _ => &self.config.channels,
+        };
+
+        // The cache is only filled some time after connecting, so the channels are always fetched.
+        let mut channels = guild_id.channels(&gateway.http).await?;
+        if ids.is_empty() {
+            return Ok(channels
+                .into_values()
+                .filter(|c| c.name == "veloren-updates")
+                .collect());
+        }
+
+        // The global channels belong to every guild, so most of them aren't in this one.
+        Ok(ids
+            .iter()
+            .filter_map(|&id| channels.remove(&ChannelId(id)))
+            .collect())
+    }
+
+    // Add a row of buttons linking to the configured pages. Discord allows up to five buttons in a
+    // row, so any others are left out. Only Veloren's own pages are known for the other projects.
+    fn links(&self, buttons: &mut CreateComponents, announcement: &Announcement) {
+        let language = announcement.language();
+        let links = match &self.config.links {
+            Some(links) => links.clone(),
+            None if announcement.project.is_some() => vec![],
+            None => DEFAULT_LINKS
+                .iter()
+                .map(|(key, url)| LinkConfig {
+                    label: locale::text(language, key, &[]),
+                    url: url.to_string(),
+                })
+                .collect(),
+        };
+        if links.is_empty() {
+            return;
+        }
+
+        buttons.create_action_row(|r| {
+            for link in links.iter().take(5) {
+                r.create_button(|b| b.style(ButtonStyle::Link).label(&link.label).url(&link.url));
+            }
+            r
+        });
+    }
+
+    // The message for the guild. The roles are mentioned on the first page, since only the first
+    // page is posted.
+    fn content(&self, guilds: &Guilds, guild_id: GuildId, announcement: &Announcement) -> Content {
+        let style = guilds.get(guild_id.0).style;
+        let message = announcement.map_entries(markdown::escape).message();
+        let lines = message.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+        let mut pages = split(&lines, MAX_PAGE_LENGTH);
+        let mut file = None;
+        if style == Style::Digest {
+            pages = vec![summary(announcement, "digest").join("\n")];
+        } else if pages.len() > MAX_INLINE_MESSAGES {
+            pages = vec![summary(announcement, "attached").join("\n")];
+            file = Some(message);
+        } else if pages.is_empty() {
+            pages.push(String::new());
+        }
+
+        let content = &mut pages[0];
+        let mut roles = match self.config.guilds.get(&guild_id.to_string()) {
+            Some(guild) => mentions(guild, announcement),
+            None => vec![],
+        };
+        if !roles.is_empty() {
+            content.push('\n');
+            content.push_str(
+                &roles
+                    .iter()
+                    .map(|r| r.mention().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        }
+
+        // The ping role goes first, so that it shows up in the notification.
+        if let Some(role) = guilds.get(guild_id.0).ping_role {
+            let role = RoleId(role);
+            *content = format!("{}\n{}", role.mention(), content);
+            roles.push(role);
+        }
+
+        Content {
+            pages,
+            roles,
+            file,
+            style,
+        }
+    }
+
+    // Add the new entries to the latest announcement in the channel if it was posted within the
+    // edit window, returning the edited message. Announcements with embeds or release notes are
+    // always posted as new messages, and so is everything if the latest announcement can't be
+    // edited anymore.
+    async fn edit(
+        &self,
+        http: &Http,
+        guilds: &Guilds,
+        announcement: &Announcement,
+        channel: &GuildChannel,
+    ) -> Option<Message> {
+        let window = (self.config.edit_window_hours * 3600) as i64;
+        if window == 0
+            || channel.kind == ChannelType::Forum
+            || !embeds(announcement).is_empty()
+            || !announcement.releases.is_empty()
+        {
+            return None;
+        }
+
+        let previous = guilds
+            .get(channel.guild_id.0)
+            .announcements
+            .get(&channel.id.0)
+            .cloned()?;
+        if previous.changes.is_empty()
+            || previous.project != announcement.project
+            || chrono::Utc::now().timestamp() - previous.posted_at > window
+        {
+            return None;
+        }
+
+        // An announcement that needs pages is posted anew, since the buttons can't be added later.
+        let merged = announcement.merge(&previous.changes);
+        let content = self.content(guilds, channel.guild_id, &merged);
+        let ([content], None) = (&content.pages[..], &content.file) else {
+            return None;
+        };
+        let message_id = MessageId(previous.message);
+        let result = retry(&self.retry, || {
+            channel
+                .id
+                .edit_message(http, message_id, |m| m.content(content))
+        })
+        .await;
+
+        match result {
+            Ok(message) => {
+                guilds.update(channel.guild_id.0, |g| {
+                    g.announcements.insert(
+                        channel.id.0,
+                        Posted {
+                            changes: merged.changes.clone(),
+                            ..previous
+                        },
+                    );
+                });
+                Some(message)
+            }
+            Err(e) => {
+                tracing::debug!(channel = %channel.id, error = %e, "Unable to edit the previous announcement");
+                None
+            }
+        }
+    }
+
+    // Only the given roles can be mentioned, so that entries can't ping anyone by accident. Only
+    // the first page is posted, with buttons to browse the others.
+    async fn send(
+        &self,
+        http: &Http,
+        announcement: &Announcement,
+        content: &Content,
+        channel: &GuildChannel,
+    ) -> serenity::Result<Vec<Message>> {
+        let roles = &content.roles;
+        let page = Page::of(
+            &content.pages,
+            0,
+            "announcement",
+            "",
+            announcement.language(),
+        );
+        let file = content
+            .file
+            .as_ref()
+            .map(|f| attachment(f, "veloren-news.md"));
+        let chart = announcement.chart.as_ref().map(|c| AttachmentType::Bytes {
+            data: c.clone().into(),
+            filename: "activity.png".to_string(),
+        });
+        let style = content.style;
+        let content = page.content.as_str();
+        let mut buttons = page.buttons;
+        if style != Style::Compact {
+            self.links(&mut buttons, announcement);
+        }
+
+        // A message can only hold a limited number of embeds, so any remaining embeds are sent as
+        // follow-up messages.
+        let mut embeds = match style {
+            Style::Full => embeds(announcement),
+            Style::Compact | Style::Digest => vec![],
+        };
+        let rest = embeds.split_off(embeds.len().min(MAX_EMBEDS));
+
+        // Forum channels only hold posts, so the announcement starts a new post there, which the
+        // follow-up messages then go to.
+        let date = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let name = locale::text(
+            announcement.language(),
+            "forum-post-name",
+            &[("date", &date)],
+        );
+        let mut messages = vec![];
+        let target = match channel.kind {
+            ChannelType::Forum => {
+                let post = retry(&self.retry, || {
+                    forum_post(http, channel, &name, content, &embeds, &buttons, roles)
+                })
+                .await?;
+                let message =
+                    retry(&self.retry, || post.message(http, MessageId(post.id.0))).await?;
+                messages.push(message);
+
+                // Forum posts are started without files, so the files follow.
+                if file.is_some() || chart.is_some() {
+                    let message = retry(&self.retry, || {
+                        post.send_message(http, |m| m.add_files(file.iter().chain(&chart).cloned()))
+                    })
+                    .await?;
+                    messages.push(message);
+                }
+                post
+            }
+            _ => {
+                let message = retry(&self.retry, || {
+                    channel.send_message(http, |m| {
+                        m.content(content)
+                            .add_embeds(embeds.clone())
+                            .set_components(buttons.clone())
+                            .allowed_mentions(|a| a.empty_parse().roles(roles.iter().copied()));
+                        m.add_files(file.iter().chain(&chart).cloned())
+                    })
+                })
+                .await?;
+                messages.push(message);
+                channel.clone()
+            }
+        };
+
+        for chunk in rest.chunks(MAX_EMBEDS) {
+            let message = retry(&self.retry, || {
+                target.send_message(http, |m| {
+                    m.add_embeds(chunk.to_vec())
+                        .allowed_mentions(|a| a.empty_parse())
+                })
+            })
+            .await?;
+            messages.push(message);
+        }
+
+        // The notes of a release are too long for the channel, so they go into a thread under the
+        // announcement, or into the forum post. The announcement has been posted either way, so
+        // failing to post the notes doesn't fail the delivery.
+        if !announcement.releases.is_empty() && style == Style::Full {
+            let result = match channel.kind {
+                ChannelType::Forum => self.notes(http, announcement, &target).await,
+                _ => self.thread(http, announcement, channel, &messages[0]).await,
+            };
+
+            if let Err(e) = result {
+                tracing::warn!(channel = %channel.id, error = %e, "Unable to post the release notes");
+            }
+        }
+
+        // Publish the messages to the servers following an announcement channel. The messages have
+        // been posted either way, so failing to publish them doesn't fail the delivery.
+        if channel.kind == ChannelType::News {
+            for message in &messages {
+                if let Err(e) = retry(&self.retry, || message.crosspost(http)).await {
+                    tracing::warn!(channel = %channel.id, error = %e, "Unable to publish the message");
+                }
+            }
+        }
+
+        Ok(messages)
+    }
+
+    // Correct an announcement that was posted with entries that have been removed since. With
+    // `edit`, the first message is edited if the correction still fits into it. Otherwise its
+    // messages are deleted, and the correction is posted in their place unless nothing is left of
+    // the announcement.
+    async fn amend(
+        &self,
+        gateway: &Gateway,
+        guild_id: GuildId,
+        delivered: &Delivered,
+        announcement: &Announcement,
+        entries: &[String],
+        mode: Corrections,
+    ) -> serenity::Result<()> {
+        let http = &gateway.http;
+        let guilds = &gateway.guilds;
+        let channel = ChannelId(delivered.channel);
+        let corrected = announcement.without(entries).corrected();
+        let content = self.content(guilds, guild_id, &corrected);
+
+        let first = delivered.messages.first().copied().map(MessageId);
+        let fits = matches!((&content.pages[..], &content.file), ([_], None));
+        if let (Corrections::Edit, Some(first), true) = (mode, first, fits && !corrected.is_empty())
+        {
+            let page = &content.pages[0];
+            retry(&self.retry, || {
+                channel.edit_message(http, first, |m| m.content(page))
+            })
+            .await?;
+
+            guilds.update(guild_id.0, |g| {
+                let ledger = g.ledger.iter_mut();
+                for d in ledger.filter(|d| d.hash == delivered.hash && d.channel == channel.0) {
+                    d.announcement = Some(corrected.clone());
+                }
+                if let Some(posted) = g.announcements.get_mut(&channel.0) {
+                    if posted.message == first.0 {
+                        posted.changes = corrected.changes.clone();
+                    }
+                }
+            });
+            return Ok(());
+        }
+
+        for &message in &delivered.messages {
+            let result = retry(&self.retry, || {
+                channel.delete_message(http, MessageId(message))
+            })
+            .await;
+            if let Err(e) = result {
+                tracing::debug!(message, error = %e, "Unable to delete the announcement");
+            }
+        }
+        guilds.update(guild_id.0, |g| {
+            g.ledger
+                .retain(|d| d.hash != delivered.hash || d.channel != channel.0);
+            if g.announcements
+                .get(&channel.0)
+                .is_some_and(|p| delivered.messages.contains(&p.message))
+            {
+                g.announcements.remove(&channel.0);
+            }
+        });
+
+        if corrected.is_empty() {
+            return Ok(());
+        }
+        let now = chrono::Utc::now().timestamp();
+        let delivery = self
+            .post(gateway, &corrected, guild_id, Some(channel), now)
+            .await;
+        match delivery.sent {
+            0 => Err(serenity::Error::Other("Unable to post the correction")),
+            _ => Ok(()),
+        }
+    }
+
+    // Send the announcement to a subscriber, split into as many messages as it takes since there
+    // are no pages to browse in direct messages.
+    async fn direct(
+        &self,
+        http: &Http,
+        announcement: &Announcement,
+        user: UserId,
+    ) -> serenity::Result<()> {
+        let channel = retry(&self.retry, || user.create_dm_channel(http)).await?;
+        let message = announcement.map_entries(markdown::escape).message();
+        let lines = message.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+
+        for page in split(&lines, MAX_LENGTH) {
+            retry(&self.retry, || {
+                channel.send_message(http, |m| {
+                    m.content(&page).allowed_mentions(|a| a.empty_parse())
+                })
+            })
+            .await?;
+        }
+
+        for chunk in embeds(announcement).chunks(MAX_EMBEDS) {
+            retry(&self.retry, || {
+                channel.send_message(http, |m| {
+                    m.add_embeds(chunk.to_vec())
+                        .allowed_mentions(|a| a.empty_parse())
+                })
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    // Remember the announcement as the latest in the channel, and move the pin to it if enabled.
+    // The announcement has been posted either way, so failing to pin it is only logged.
+    async fn posted(
+        &self,
+        http: &Http,
+        guilds: &Guilds,
+        announcement: &Announcement,
+        channel: &GuildChannel,
+        message: &Message,
+    ) {
+        let previous = guilds
+            .get(channel.guild_id.0)
+            .announcements
+            .get(&channel.id.0)
+            .map(|p| MessageId(p.message));
+
+        guilds.update(channel.guild_id.0, |g| {
+            g.announcements.insert(
+                channel.id.0,
+                Posted {
+                    message: message.id.0,
+                    posted_at: message.timestamp.unix_timestamp(),
+                    changes: announcement.changes.clone(),
+                    project: announcement.project.clone(),
+                },
+            );
+        });
+
+        // Forum posts are pinned in the forum instead, which isn't supported.
+        if !self.config.pin || channel.kind == ChannelType::Forum {
+            return;
+        }
+
+        if let Some(previous) = previous {
+            // The previous announcement may have been unpinned or deleted by hand.
+            if let Err(e) = channel.id.unpin(http, previous).await {
+                tracing::debug!(channel = %channel.id, error = %e, "Unable to unpin the previous announcement");
+            }
+        }
+
+        if let Err(e) = retry(&self.retry, || message.pin(http)).await {
+            tracing::warn!(channel = %channel.id, error = %e, "Unable to pin the announcement");
+        }
+    }
+
+    async fn thread(
+        &self,
+        http: &Http,
+        announcement: &Announcement,
+        channel: &GuildChannel,
+        message: &Message,
+    ) -> serenity::Result<()> {
+        let versions = announcement
+            .releases
+            .iter()
+            .map(|r| r.version.as_str())
+            .collect::<Vec<_>>();
+        let name = locale::text(
+            announcement.language(),
+            "thread-name",
+            &[("versions", &versions.join(", "))],
+        );
+        let name = name
+            .chars()
+            .take(MAX_THREAD_NAME_LENGTH)
+            .collect::<String>();
+
+        let thread = retry(&self.retry, || {
+            channel.create_public_thread(http, message.id, |t| t.name(&name))
+        })
+        .await?;
+
+        self.notes(http, announcement, &thread).await
+    }
+
+    // Post the notes of every release to the thread.
+    async fn notes(
+        &self,
+        http: &Http,
+        announcement: &Announcement,
+        thread: &GuildChannel,
+    ) -> serenity::Result<()> {
+        let mut lines = vec![];
+        for release in &announcement.releases {
+            lines.push(format!("# {} {}", announcement.name, release.version));
+            lines.extend(release.notes.iter().cloned());
+            lines.extend(release.compare.iter().map(|url| format!("<{}>", url)));
+        }
+
+        // Notes that would flood the thread are attached as files instead, one for each release,
+        // with only the number of entries in each section shown.
+        let chunks = split(&lines, MAX_LENGTH);
+        if chunks.len() > MAX_INLINE_MESSAGES {
+            for release in &announcement.releases {
+                let mut lines = vec![format!("# {} {}", announcement.name, release.version)];
+                lines.extend(counts(
+                    &changelog::sections(&release.notes),
+                    announcement.language(),
+                ));
+                lines.push(locale::text(announcement.language(), "attached", &[]));
+                let content = lines.join("\n");
+                let file = attachment(
+                    &release.notes.join("\n"),
+                    &format!(
+                        "{}-{}.md",
+                        announcement.name.to_lowercase(),
+                        release.version
+                    ),
+                );
+
+                retry(&self.retry, || {
+                    thread.send_message(http, |m| {
+                        m.content(&content)
+                            .add_file(file.clone())
+                            .allowed_mentions(|a| a.empty_parse())
+                    })
+                })
+                .await?;
+            }
+            return Ok(());
+        }
+
+        for chunk in chunks {
+            retry(&self.retry, || {
+                thread.send_message(http, |m| {
+                    m.content(&chunk).allowed_mentions(|a| a.empty_parse())
+                })
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+// Start a post in a forum channel with the message. Serenity has no builder for forum posts, but
+// they are created through the same endpoint as threads without a starter message.
+async fn forum_post(
+    http: &Http,
+    channel: &GuildChannel,
+    name: &str,
+    content: &str,
+    embeds: &[CreateEmbed],
+    buttons: &CreateComponents,
+    roles: &[RoleId],
+) -> serenity::Result<GuildChannel> {
+    let embeds = embeds
+        .iter()
+        .map(|e| Value::Object(hashmap_to_json_map(e.0.clone())))
+        .collect::<Vec<_>>();
+
+    let post = json!({
+        "name": name,
+        "message": {
+            "content": content,
+            "embeds": embeds,
+            "components": buttons.0,
+            "allowed_mentions": { "parse": [], "roles": roles },
+        },
+    });
+
+    http.create_private_thread(channel.id.0, post.as_object().unwrap())
+        .await
+}
+
+// Whether the request failed because what it was about doesn't exist (anymore).
+fn unknown(e: &serenity::Error) -> bool {
+    match e {
+        serenity::Error::Http(e) => match e.as_ref() {
+            HttpError::UnsuccessfulRequest(r) => r.status_code.as_u16() == 404,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// The permissions that the bot lacks to post the announcement in the channel. The permissions are
+// worked out from the cache, and if that isn't possible posting is attempted anyway.
+fn missing_permissions(
+    gateway: &Gateway,
+    announcement: &Announcement,
+    content: &Content,
+    channel: &GuildChannel,
+) -> Permissions {
+    let mut required = Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES;
+    if !embeds(announcement).is_empty() && content.style == Style::Full {
+        required |= Permissions::EMBED_LINKS;
+    }
+    if content.file.is_some() {
+        required |= Permissions::ATTACH_FILES;
+    }
+
+    match channel.permissions_for_user(&gateway.cache, gateway.cache.current_user_id()) {
+        Ok(permissions) => required - permissions,
+        Err(e) => {
+            tracing::debug!(channel = %channel.id, error = %e, "Unable to work out the permissions");
+            Permissions::empty()
+        }
+    }
+}
+
+// A markdown file to attach to a message.
+fn attachment(markdown: &str, filename: &str) -> AttachmentType<'static> {
+    AttachmentType::Bytes {
+        data: markdown.as_bytes().to_vec().into(),
+        filename: filename.to_string(),
+    }
+}
+
+// The heading and the number of entries in each section of an announcement that is only
+// summarized, followed by the note with the given key on where the entries are.
+fn summary(announcement: &Announcement, note: &str) -> Vec<String> {
+    let sections = announcement
+        .sections()
+        .into_iter()
+        .map(|s| Update::Section {
+            title: s.title.to_string(),
+            entries: s.entries.iter().map(|e| e.to_string()).collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut lines = vec![format!("# {}", announcement.heading())];
+    lines.extend(counts(&sections, announcement.language()));
+    lines.push(locale::text(announcement.language(), note, &[]));
+    lines
+}
+
+// A line with the number of entries of each section.
+fn counts(sections: &[Update], language: Option<&str>) -> Vec<String> {
+    sections
+        .iter()
+        .filter_map(|u| match u {
+            Update::Section { title, entries } if !entries.is_empty() => Some(locale::text(
+                language,
+                "section-count",
+                &[("section", title), ("count", &entries.len().to_string())],
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+// Remember that the announcement has been posted to the channel, forgetting what was posted too long
+// ago.
+fn record(
+    guilds: &Guilds,
+    channel: &GuildChannel,
+    announcement: &Announcement,
+    messages: &[Message],
+) {
+    let now = chrono::Utc::now().timestamp();
+    guilds.update(channel.guild_id.0, |g| {
+        g.ledger.retain(|d| now - d.posted_at <= MAX_LEDGER_AGE);
+        g.ledger.push(Delivered {
+            hash: announcement.hash(),
+            channel: channel.id.0,
+            messages: messages.iter().map(|m| m.id.0).collect(),
+            posted_at: now,
+            announcement: Some(announcement.clone()),
+        });
+    });
+}
+
+// Post an announcement of the ledger to the channel again for `/repost`, as a new message even if
+// it's still in the channel.
+async fn repost(
+    context: &Context,
+    guilds: &Arc<Guilds>,
+    config: &DiscordConfig,
+    retry: &RetryConfig,
+    announcement: &Announcement,
+    guild_id: GuildId,
+    channel: ChannelId,
+) -> Delivery {
+    let hash = announcement.hash();
+    guilds.update(guild_id.0, |g| {
+        g.ledger
+            .retain(|d| d.hash != hash || d.channel != channel.0);
+        g.announcements.remove(&channel.0);
+    });
+
+    let gateway = Arc::new(Gateway {
+        http: context.http.clone(),
+        cache: context.cache.clone(),
+        shard_manager: None,
+        guilds: guilds.clone(),
+    });
+    let sink = DiscordSink::new(
+        config.clone(),
+        retry.clone(),
+        Discord::Gateway(gateway.clone()),
+    );
+    let now = chrono::Utc::now().timestamp();
+    sink.post(&gateway, announcement, guild_id, Some(channel), now)
+        .await
+}
+
+// Remember the pages of an announcement so that they can be browsed, forgetting the oldest ones.
+fn remember_pages(guilds: &Guilds, guild_id: GuildId, message: &Message, pages: Vec<String>) {
+    guilds.update(guild_id.0, |g| {
+        g.pages.insert(message.id.0, pages);
+        // Message IDs grow over time, so the first ones are the oldest.
+        while g.pages.len() > MAX_PAGED {
+            g.pages.pop_first();
+        }
+    });
+}
+
+// Join the lines into as few messages as possible without splitting a line, unless the line itself
+// is too long.
+pub fn split(lines: &[String], max_length: usize) -> Vec<String> {
+    let mut messages: Vec<String> = vec![];
+
+    for line in lines {
+        let line = line.chars().take(max_length).collect::<String>();
+        match messages.last_mut() {
+            Some(last) if last.chars().count() + line.chars().count() < max_length => {
+                last.push('\n');
+                last.push_str(&line);
+            }
+            _ => messages.push(line),
+        }
+    }
+
+    messages
+}
+
+#[async_trait]
+impl Sink for DiscordSink {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    // Post the announcement to the target channels of every guild, connecting first if needed.
+    async fn publish(&self, announcement: &Announcement) -> Delivery {
+        let Some(gateway) = self.connect().await else {
+            return Delivery {
+                failed: vec!["Discord".to_string()],
+                ..Default::default()
+            };
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        // Every language is only translated once, however many guilds use it, before posting to
+        // the guilds.
+        let mut localized: BTreeMap<String, Announcement> = BTreeMap::new();
+
+        let mut targets = vec![];
+        for guild_id in gateway.cache.guilds() {
+            // Otherwise anyone could have the bot post to their guild by inviting it.
+            if !self.config.allows(guild_id.0) {
+                tracing::debug!(guild = %guild_id, "Guild is not allowed");
+                continue;
+            }
+
+            if gateway.guilds.get(guild_id.0).paused {
+                tracing::debug!(guild = %guild_id, "Announcements are paused");
+                continue;
+            }
+
+            let language = language(&self.config, &gateway.guilds, guild_id)
+                .filter(|l| l != locale::DEFAULT_LANGUAGE);
+            if let Some(language) = language.as_ref().filter(|l| !localized.contains_key(*l)) {
+                let l = self.localize(announcement, language).await;
+                localized.insert(language.clone(), l);
+            }
+            targets.push((guild_id, language));
+        }
+
+        let semaphore = self.semaphore();
+        let deliveries = targets.iter().map(|(guild_id, language)| {
+            let announcement = language.as_ref().map_or(announcement, |l| &localized[l]);
+            let span = tracing::info_span!("post", guild = %guild_id);
+            let semaphore = &semaphore;
+            let gateway = &gateway;
+            async move {
+                let _permit = semaphore
+                    .acquire()
+                    .await
+                    .expect("Unable to wait for a guild.");
+                self.post(gateway, announcement, *guild_id, None, now).await
+            }
+            .instrument(span)
+        });
+
+        let mut delivery = Delivery::default();
+        for guild_delivery in future::join_all(deliveries).await {
+            delivery.merge(guild_delivery);
+        }
+
+        // Events are only created when the release is first announced, since redelivering is
+        // too late for them.
+        if let Some(events) = &self.config.events {
+            let releasing = !announcement.releases.is_empty() || !announcement.completed.is_empty();
+            for (guild_id, language) in targets.iter().filter(|_| releasing) {
+                if !events.guilds.contains(&guild_id.0) {
+                    continue;
+                }
+                let span = tracing::info_span!("events", guild = %guild_id);
+                events::create(
+                    &gateway.http,
+                    events,
+                    announcement,
+                    *guild_id,
+                    language.as_deref(),
+                )
+                .instrument(span)
+                .await;
+            }
+        }
+
+        // Direct messages aren't queued like channels, so a subscriber that can't be reached
+        // doesn't fail the delivery, and is unsubscribed after failing a few times in a row.
+        for (user, subscriber) in gateway.guilds.subscribers() {
+            let announcement = match subscriber.language {
+                Some(language) if language != locale::DEFAULT_LANGUAGE => {
+                    if !localized.contains_key(&language) {
+                        let l = self.localize(announcement, &language).await;
+                        localized.insert(language.clone(), l);
+                    }
+                    &localized[&language]
+                }
+                _ => announcement,
+            };
+
+            let span = tracing::info_span!("direct", user);
+            let result = self
+                .direct(&gateway.http, announcement, UserId(user))
+                .instrument(span)
+                .await;
+            match &result {
+                Ok(()) => delivery.sent += 1,
+                Err(e) => tracing::warn!(user, error = %e, "Unable to message the subscriber"),
+            }
+
+            if gateway.guilds.delivered(user, result.is_ok()) {
+                tracing::info!(user, "Unsubscribed the unreachable subscriber");
+            }
+        }
+
+        self.disconnect(gateway).await;
+
+        tracing::info!(
+            guilds = targets.len(),
+            sent = delivery.sent,
+            failed = delivery.failed.len(),
+            queued = delivery.queued.len(),
+            "Posted to Discord"
+        );
+        delivery
+    }
+
+    // Post the queued announcements of every guild, oldest first. Announcements that have been
+    // queued for too long are dropped, since they are outdated by then.
+    async fn redeliver(&self) -> Delivery {
+        let guilds = self.discord.guilds();
+
+        let queued = guilds
+            .all()
+            .into_iter()
+            .filter(|(id, g)| !g.undelivered.is_empty() && !g.paused && self.config.allows(*id))
+            .collect::<Vec<_>>();
+        if queued.is_empty() {
+            return Delivery::default();
+        }
+
+        let Some(gateway) = self.connect().await else {
+            return Delivery {
+                failed: vec!["Discord".to_string()],
+                ..Default::default()
+            };
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        // The announcements of each guild are posted in order, while the guilds are posted to
+        // like with new announcements.
+        let semaphore = self.semaphore();
+        let deliveries = queued.into_iter().map(|(guild, settings)| {
+            let guild_id = GuildId(guild);
+            let span = tracing::info_span!("redeliver", guild = %guild_id);
+            let semaphore = &semaphore;
+            let gateway = &gateway;
+            let guilds = &guilds;
+            async move {
+                let _permit = semaphore
+                    .acquire()
+                    .await
+                    .expect("Unable to wait for a guild.");
+
+                // Anything that fails again is queued again by posting.
+                guilds.update(guild, |g| g.undelivered.clear());
+
+                let mut delivery = Delivery::default();
+                for undelivered in settings.undelivered {
+                    if now - undelivered.queued_at > MAX_UNDELIVERED_AGE {
+                        tracing::warn!("Dropping an outdated undelivered announcement");
+                        continue;
+                    }
+
+                    let guild_delivery = self.post(
+                        gateway,
+                        &undelivered.announcement,
+                        guild_id,
+                        undelivered.channel.map(ChannelId),
+                        undelivered.queued_at,
+                    );
+                    delivery.merge(guild_delivery.await);
+                }
+                delivery
+            }
+            .instrument(span)
+        });
+
+        let mut delivery = Delivery::default();
+        for guild_delivery in future::join_all(deliveries).await {
+            delivery.merge(guild_delivery);
+        }
+
+        self.disconnect(gateway).await;
+
+        tracing::info!(
+            sent = delivery.sent,
+            queued = delivery.queued.len(),
+            "Redelivered to Discord"
+        );
+        delivery
+    }
+
+    // Correct the announcements of the ledger that had any of the entries. Translated announcements
+    // can't be told to have them, so only those in English are corrected.
+    async fn correct(&self, entries: &[String]) -> Delivery {
+        let Some(mode) = self.config.corrections else {
+            return Delivery::default();
+        };
+
+        let affected = self
+            .discord
+            .guilds()
+            .all()
+            .into_iter()
+            .filter(|(id, _)| self.config.allows(*id))
+            .flat_map(|(id, g)| g.ledger.into_iter().map(move |d| (id, d)))
+            .filter_map(|(id, d)| {
+                let announcement = d.announcement.clone()?;
+                let changed = announcement.without(entries).changes != announcement.changes;
+                changed.then_some((id, d, announcement))
+            })
+            .collect::<Vec<_>>();
+        if affected.is_empty() {
+            return Delivery::default();
+        }
+
+        let Some(gateway) = self.connect().await else {
+            return Delivery {
+                failed: vec!["Discord".to_string()],
+                ..Default::default()
+            };
+        };
+
+        let mut delivery = Delivery::default();
+        for (guild, delivered, announcement) in affected {
+            let span = tracing::info_span!("correct", guild, channel = delivered.channel);
+            let result = self
+                .amend(
+                    &gateway,
+                    GuildId(guild),
+                    &delivered,
+                    &announcement,
+                    entries,
+                    mode,
+                )
+                .instrument(span)
+                .await;
+            if let Err(e) = &result {
+                tracing::warn!(channel = delivered.channel, error = %e, "Unable to correct the announcement");
+            }
+
+            let target = format!("Channel {} in guild {}", delivered.channel, guild);
+            delivery.record(target, &result);
+        }
+
+        self.disconnect(gateway).await;
+
+        tracing::info!(
+            sent = delivery.sent,
+            failed = delivery.failed.len(),
+            "Corrected on Discord"
+        );
+        delivery
+    }
+}
+
+// Name of the token file in the working directory, and of the token's environment variable.
+const TOKEN_NAME: &str = "DISCORD_TOKEN";
+
+// Name of the credential when running as a systemd service with `LoadCredential=`.
+const TOKEN_CREDENTIAL: &str = "discord-token";
+
+// Find the bot token, looking in order at the given file, the `DISCORD_TOKEN` environment
+// variable, the systemd credentials, and the `DISCORD_TOKEN` file in the working directory.
+pub fn token(token_file: Option<&str>) -> Result<String, String> {
+    let read = |path: &str| {
+        fs::read_to_string(path)
+            .map(|s| s.trim().to_string())
+            .map_err(|e| format!("Unable to read the Discord token from {}: {}", path, e))
+    };
+
+    if let Some(path) = token_file {
+        return read(path);
+    }
+
+    if let Ok(token) = env::var(TOKEN_NAME) {
+        return Ok(token.trim().to_string());
+    }
+
+    if let Ok(dir) = env::var("CREDENTIALS_DIRECTORY") {
+        let path = Path::new(&dir).join(TOKEN_CREDENTIAL);
+        if path.exists() {
+            return read(&path.to_string_lossy());
+        }
+    }
+
+    if Path::new(TOKEN_NAME).exists() {
+        return read(TOKEN_NAME);
+    }
+
+    Err(format!(
+        "No Discord token found. Pass --token-file, set the {} environment variable, provide the \
+         {} systemd credential, or create a {} file.",
+        TOKEN_NAME, TOKEN_CREDENTIAL, TOKEN_NAME
+    ))
+}
+
+// Post the announcement for review to the staging channel, returning the ID of the message whose
+// buttons approve or reject it, or nothing if it couldn't be posted.
+pub async fn stage(
+    config: &DiscordConfig,
+    retry: &RetryConfig,
+    staging: &StagingConfig,
+    announcement: &Announcement,
+    discord: Discord,
+) -> Option<u64> {
+    let sink = DiscordSink::new(config.clone(), retry.clone(), discord);
+    let gateway = sink.connect().await?;
+
+    let result = staging::post(&gateway.http, staging, announcement).await;
+    sink.disconnect(gateway).await;
+    match result {
+        Ok(message) => Some(message.0),
+        Err(e) => {
+            tracing::error!(error = %e, "Unable to post the announcement for review");
+            None
+        }
+    }
+}
+
+// Note in the staging channel that the announcement of the message wasn't reviewed in time.
+pub async fn expire(
+    config: &DiscordConfig,
+    retry: &RetryConfig,
+    staging: &StagingConfig,
+    message: u64,
+    discord: Discord,
+) {
+    let sink = DiscordSink::new(config.clone(), retry.clone(), discord);
+    if let Some(gateway) = sink.connect().await {
+        staging::expire(&gateway.http, staging, MessageId(message)).await;
+        sink.disconnect(gateway).await;
+    }
+}
+
+// A connection to Discord, which stays open until shut down.
+pub struct Gateway {
+    http: Arc<Http>,
+    cache: Arc<Cache>,
+    // Unset for the connection that a command is answered over, which the command can't close.
+    shard_manager: Option<Arc<Mutex<ShardManager>>>,
+    guilds: Arc<Guilds>,
+}
+
+impl Gateway {
+    // Start the bot and wait until it is ready, or return `None` if it stopped before that. Slash
+    // commands are only registered if `commands` is set, since they can only be answered while
+    // the bot stays connected. Approving an announcement requests a check with `requested`.
+    pub async fn connect(
+        token: &str,
+        guilds: Arc<Guilds>,
+        config: DiscordConfig,
+        retry: RetryConfig,
+        commands: bool,
+        requested: Option<Arc<Notify>>,
+    ) -> Option<Gateway> {
+        let (ready, on_ready) = oneshot::channel();
+        let builder = match config.proxy {
+            true => ClientBuilder::new_with_http(
+                HttpBuilder::new(token)
+                    .client(http::client().clone())
+                    .build(),
+                GatewayIntents::default(),
+            ),
+            false => Client::builder(token, GatewayIntents::default()),
+        };
+        let mut client = builder
+            .event_handler(Handler {
+                ready: Mutex::new(Some(ready)),
+                guilds: guilds.clone(),
+                config,
+                retry,
+                commands,
+                requested,
+            })
+            .await
+            .expect("Unable to start the bot.");
+
+        let gateway = Gateway {
+            http: client.cache_and_http.http.clone(),
+            cache: client.cache_and_http.cache.clone(),
+            shard_manager: Some(client.shard_manager.clone()),
+            guilds,
+        };
+
+        // The client is dropped along with the handler when it stops, which cancels `on_ready`.
+        tokio::spawn(async move {
+            if let Err(e) = client.start().await {
+                tracing::error!(error = ?e, "Bot crashed");
+            }
+            METRICS.gateway_connected(false);
+        });
+
+        on_ready.await.ok().map(|()| gateway)
+    }
+
+    pub async fn shutdown(&self) {
+        if let Some(shard_manager) = &self.shard_manager {
+            shard_manager.lock().await.shutdown_all().await;
+        }
+    }
+}
+
+// The message for a guild.
+struct Content {
+    // The pages of the message, of which only the first is posted.
+    pages: Vec<String>,
+    // The roles that the message mentions.
+    roles: Vec<RoleId>,
+    // The whole message, when it's too long even to browse and the page only summarizes it.
+    file: Option<String>,
+    style: Style,
+}
+
+struct Handler {
+    // Taken on the first ready event, reconnecting only updates the connection status.
+    ready: Mutex<Option<oneshot::Sender<()>>>,
+    guilds: Arc<Guilds>,
+    config: DiscordConfig,
+    retry: RetryConfig,
+    commands: bool,
+    requested: Option<Arc<Notify>>,
+}
+
+// The language chosen in the guild, or else the one configured for it.
+fn language(config: &DiscordConfig, guilds: &Guilds, guild_id: GuildId) -> Option<String> {
+    guilds.get(guild_id.0).language.or_else(|| {
+        config
+            .guilds
+            .get(&guild_id.to_string())
+            .and_then(|g| g.language.clone())
+    })
+}
+
+// The roles of the guild whose keywords appear in any of the new entries.
+fn mentions(guild: &GuildConfig, announcement: &Announcement) -> Vec<RoleId> {
+    let entries = announcement
+        .changes
+        .iter()
+        .filter(|l| l.starts_with("- "))
+        .map(|l| l.to_lowercase())
+        .collect::<Vec<_>>();
+
+    let mut roles = guild
+        .mentions
+        .iter()
+        .filter(|(keyword, _)| {
+            let keyword = keyword.to_lowercase();
+            entries.iter().any(|e| e.contains(&keyword))
+        })
+        .map(|(_, role)| RoleId(*role))
+        .collect::<Vec<_>>();
+
+    roles.sort();
+    roles.dedup();
+    roles
+}
+
+fn embed(devblog: &Devblog) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed.title(&devblog.title).url(&devblog.url);
+
+    if let Some(excerpt) = &devblog.excerpt {
+        embed.description(excerpt);
+    }
+
+    if let Some(image) = &devblog.image {
+        embed.image(image);
+    }
+
+    // Dates without a time component are assumed to be at midnight UTC.
+    if let Some(date) = &devblog.date {
+        match Timestamp::parse(date).or_else(|_| Timestamp::parse(&format!("{date}T00:00:00Z"))) {
+            Ok(t) => embed.timestamp(t),
+            Err(_) => embed.footer(|f| f.text(date)),
+        };
+    }
+
+    embed
+}
+
+// Everything of the announcement that is shown as an embed below the message.
+fn embeds(announcement: &Announcement) -> Vec<CreateEmbed> {
+    announcement
+        .devblogs
+        .iter()
+        .map(embed)
+        .chain(
+            announcement
+                .releases
+                .iter()
+                .filter_map(|r| release_embed(r, announcement.language())),
+        )
+        .chain(
+            announcement
+                .artifacts
+                .iter()
+                .map(|a| artifacts_embed(a, announcement.language())),
+        )
+        .chain(
+            announcement
+                .streams
+                .iter()
+                .map(|s| stream_embed(s, announcement.language())),
+        )
+        .collect()
+}
+
+// The launcher and downloads of a tagged release, if there are any.
+fn release_embed(release: &Release, language: Option<&str>) -> Option<CreateEmbed> {
+    let mut lines = release
+        .downloads
+        .iter()
+        .map(|d| format!("[{}]({})", d.name, d.url))
+        .collect::<Vec<_>>();
+    if let Some(launcher) = &release.launcher {
+        let name = locale::text(language, "launcher", &[]);
+        lines.insert(0, format!("[{}]({})", name, launcher));
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(locale::text(
+            language,
+            "download",
+            &[("version", &release.version)],
+        ))
+        .description(lines.join("\n"));
+    if let Some(launcher) = &release.launcher {
+        embed.url(launcher);
+    }
+    Some(embed)
+}
+
+fn artifacts_embed(artifacts: &Artifacts, language: Option<&str>) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(locale::text(
+            language,
+            "download",
+            &[("version", &artifacts.version)],
+        ))
+        .url(&artifacts.url)
+        .description(
+            artifacts
+                .downloads
+                .iter()
+                .map(|d| format!("[{}]({})", d.name, d.url))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    embed
+}
+
+// Streams are shown like Discord shows Twitch links, in Twitch's colour.
+fn stream_embed(stream: &Stream, language: Option<&str>) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(TWITCH_COLOUR)
+        .author(|a| {
+            a.name(locale::text(
+                language,
+                "live",
+                &[("channel", &stream.channel)],
+            ))
+        })
+        .title(&stream.title)
+        .url(&stream.url)
+        .image(&stream.thumbnail);
+    if !stream.game.is_empty() {
+        embed.field(locale::text(language, "playing", &[]), &stream.game, true);
+    }
+    embed
+}
+
+#[serenity::async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, context: Context, _: Ready) {
+        METRICS.gateway_connected(true);
+        let Some(ready) = self.ready.lock().await.take() else {
+            return;
+        };
+
+        if self.commands {
+            if let Err(e) = commands::register(&context).await {
+                tracing::warn!(error = %e, "Unable to register the slash commands");
+            }
+        }
+
+        let _ = ready.send(());
+    }
+
+    async fn interaction_create(&self, context: Context, interaction: Interaction) {
+        match interaction {
+            Interaction::ApplicationCommand(command) => {
+                let language = command
+                    .guild_id
+                    .and_then(|g| language(&self.config, &self.guilds, g));
+                let language = language.as_deref();
+                let (guilds, config, retry) = (&self.guilds, &self.config, &self.retry);
+                commands::handle(&context, &command, guilds, config, retry, language).await;
+            }
+            Interaction::MessageComponent(component) => {
+                let language = component
+                    .guild_id
+                    .and_then(|g| language(&self.config, &self.guilds, g));
+                let language = language.as_deref();
+                let id = &component.data.custom_id;
+                if id.starts_with("role:") {
+                    roles::handle(&context, &component, &self.guilds, language).await;
+                } else if let Some(staging) = self
+                    .config
+                    .staging
+                    .as_ref()
+                    .filter(|_| id.starts_with("staging:"))
+                {
+                    let requested = self.requested.as_deref();
+                    let guilds = &self.guilds;
+                    staging::handle(&context, &component, guilds, staging, requested, language)
+                        .await;
+                } else {
+                    pages::handle(&context, &component, &self.guilds, &self.config, language).await;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn ratelimit(&self, info: RatelimitInfo) {
+        tracing::debug!(
+            path = info.path,
+            timeout = ?info.timeout,
+            global = info.global,
+            "Waiting for a Discord rate limit"
+        );
+        METRICS.rate_limited();
+    }
+
+    async fn shard_stage_update(&self, _: Context, event: ShardStageUpdateEvent) {
+        tracing::debug!(from = %event.old, to = %event.new, "Gateway connection changed");
+        METRICS.gateway_connected(event.new == ConnectionStage::Connected);
+    }
+}
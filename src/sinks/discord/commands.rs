@@ -0,0 +1,702 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use serenity::builder::CreateApplicationCommand;
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::application::command::Command;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::application::interaction::application_command::CommandDataOption;
+use serenity::model::application::interaction::application_command::CommandDataOptionValue;
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::model::id::GuildId;
+use serenity::model::id::RoleId;
+use serenity::model::mention::Mentionable;
+use serenity::model::permissions::Permissions;
+use serenity::prelude::*;
+
+use crate::config::DiscordConfig;
+use crate::config::RetryConfig;
+use crate::guilds::Guilds;
+use crate::guilds::Style;
+use crate::locale;
+use crate::sinks::discord::pages::Page;
+use crate::sinks::discord::pages::MAX_PAGE_LENGTH;
+use crate::sinks::discord::roles;
+use crate::sources::changelog;
+use crate::sources::Update;
+
+// Replace the bot's global slash commands with the current ones. Their descriptions are
+// localized with every bundle of strings.
+pub async fn register(context: &Context) -> serenity::Result<()> {
+    Command::set_global_application_commands(&context.http, |commands| {
+        commands
+            .create_application_command(|c| {
+                describe(c.name("setup"), "setup")
+                    .default_member_permissions(Permissions::MANAGE_GUILD)
+                    .dm_permission(false)
+                    .create_option(|o| {
+                        describe_option(o.name("ping-role"), "setup.ping-role")
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|o| {
+                                describe_option(o.name("role"), "setup.ping-role.role")
+                                    .kind(CommandOptionType::Role)
+                                    .required(false)
+                            })
+                    })
+                    .create_option(|o| {
+                        describe_option(o.name("style"), "setup.style")
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|o| {
+                                describe_option(o.name("style"), "setup.style.style")
+                                    .kind(CommandOptionType::String)
+                                    .required(true);
+                                for style in ["full", "compact", "digest"] {
+                                    let (english, translations) =
+                                        locale::description(&format!("setup.style.{}", style));
+                                    let translations =
+                                        translations.into_iter().map(|(l, d)| (l, d.to_string()));
+                                    o.add_string_choice_localized(english, style, translations);
+                                }
+                                o
+                            })
+                    })
+                    .create_option(|o| {
+                        describe_option(o.name("role-menu"), "setup.role-menu")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+                    .create_option(|o| {
+                        describe_option(o.name("language"), "setup.language")
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|o| {
+                                describe_option(o.name("language"), "setup.language.language")
+                                    .kind(CommandOptionType::String)
+                                    .required(true);
+                                for (code, name) in locale::languages() {
+                                    o.add_string_choice(name, code);
+                                }
+                                o
+                            })
+                    })
+            })
+            .create_application_command(|c| describe(c.name("stats"), "stats").dm_permission(false))
+            .create_application_command(|c| {
+                describe(c.name("search"), "search")
+                    .dm_permission(false)
+                    .create_option(|o| {
+                        // The query is part of the IDs of the page buttons, which are limited to
+                        // 100 characters.
+                        describe_option(o.name("query"), "search.query")
+                            .kind(CommandOptionType::String)
+                            .max_length(80)
+                            .required(true)
+                    })
+                    .create_option(|o| {
+                        describe_option(o.name("page"), "search.page")
+                            .kind(CommandOptionType::Integer)
+                            .min_int_value(1)
+                            .required(false)
+                    })
+            })
+            .create_application_command(|c| {
+                describe(c.name("changelog"), "changelog")
+                    .dm_permission(false)
+                    .create_option(|o| {
+                        describe_option(o.name("version"), "changelog.version")
+                            .kind(CommandOptionType::String)
+                            .max_length(40)
+                            .required(false)
+                    })
+            })
+            .create_application_command(|c| {
+                describe(c.name("repost"), "repost")
+                    .default_member_permissions(Permissions::MANAGE_GUILD)
+                    .dm_permission(false)
+                    .create_option(|o| {
+                        describe_option(o.name("id"), "repost.id")
+                            .kind(CommandOptionType::String)
+                            .max_length(16)
+                            .required(false)
+                    })
+            })
+            .create_application_command(|c| describe(c.name("subscribe"), "subscribe"))
+            .create_application_command(|c| describe(c.name("unsubscribe"), "unsubscribe"))
+            .create_application_command(|c| {
+                describe(c.name("veloren-news"), "veloren-news")
+                    .default_member_permissions(Permissions::MANAGE_GUILD)
+                    .dm_permission(false)
+                    .create_option(|o| {
+                        describe_option(o.name("pause"), "veloren-news.pause")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+                    .create_option(|o| {
+                        describe_option(o.name("resume"), "veloren-news.resume")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+fn describe<'a>(
+    command: &'a mut CreateApplicationCommand,
+    key: &str,
+) -> &'a mut CreateApplicationCommand {
+    let (english, translations) = locale::description(key);
+    command.description(english);
+    for (language, description) in translations {
+        command.description_localized(language, description);
+    }
+    command
+}
+
+fn describe_option<'a>(
+    option: &'a mut CreateApplicationCommandOption,
+    key: &str,
+) -> &'a mut CreateApplicationCommandOption {
+    let (english, translations) = locale::description(key);
+    option.description(english);
+    for (language, description) in translations {
+        option.description_localized(language, description);
+    }
+    option
+}
+
+// Run a slash command and reply to whoever used it in the language of the guild, without anyone
+// else seeing the reply.
+pub async fn handle(
+    context: &Context,
+    command: &ApplicationCommandInteraction,
+    guilds: &Arc<Guilds>,
+    config: &DiscordConfig,
+    retry: &RetryConfig,
+    language: Option<&str>,
+) {
+    // Subscribing works in direct messages too, while the other commands are about the guild.
+    if let "subscribe" | "unsubscribe" = command.data.name.as_str() {
+        return respond(context, command, subscribe(command, guilds)).await;
+    }
+    let Some(guild_id) = command.guild_id else {
+        return;
+    };
+
+    let reply = match (command.data.name.as_str(), command.data.options.first()) {
+        ("stats", _) => {
+            let reply = async { Page::single(stats(config, language).await) };
+            return reply_later(context, command, reply).await;
+        }
+        ("search", _) => {
+            let options = &command.data.options;
+            let query = match option(options, "query") {
+                Some(CommandDataOptionValue::String(s)) => s.as_str(),
+                _ => "",
+            };
+            let page = match option(options, "page") {
+                Some(CommandDataOptionValue::Integer(i)) => *i as usize,
+                _ => 1,
+            };
+            let reply = search(config, language, query, page.saturating_sub(1));
+            return reply_later(context, command, reply).await;
+        }
+        ("changelog", _) => {
+            let version = match option(&command.data.options, "version") {
+                Some(CommandDataOptionValue::String(s)) => s.as_str(),
+                _ => "",
+            };
+            let reply = changelog(config, language, version, 0);
+            return reply_later(context, command, reply).await;
+        }
+        ("repost", _) => {
+            let reply = repost(context, command, guilds, config, retry, guild_id, language);
+            return reply_later(context, command, reply).await;
+        }
+        ("setup", Some(o)) if o.name == "ping-role" => {
+            setup_ping_role(context, guilds, guild_id, o, language).await
+        }
+        ("setup", Some(o)) if o.name == "role-menu" => {
+            setup_role_menu(context, command, guilds, guild_id, language).await
+        }
+        ("setup", Some(o)) if o.name == "language" => setup_language(guilds, guild_id, o),
+        ("setup", Some(o)) if o.name == "style" => setup_style(guilds, guild_id, o, language),
+        ("veloren-news", Some(o)) if o.name == "pause" => {
+            set_paused(guilds, guild_id, true, language)
+        }
+        ("veloren-news", Some(o)) if o.name == "resume" => {
+            set_paused(guilds, guild_id, false, language)
+        }
+        _ => locale::text(language, "unknown-command", &[]),
+    };
+
+    respond(context, command, reply).await;
+}
+
+async fn respond(context: &Context, command: &ApplicationCommandInteraction, reply: String) {
+    let result = command
+        .create_interaction_response(&context.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(reply).ephemeral(true))
+        })
+        .await;
+
+    if let Err(e) = result {
+        tracing::warn!(command = command.data.name, error = %e, "Unable to reply to a command");
+    }
+}
+
+// Reply to a command that takes a while to answer. Discord only waits a few seconds for a reply,
+// so the reply is announced first and edited in once it's ready.
+async fn reply_later(
+    context: &Context,
+    command: &ApplicationCommandInteraction,
+    reply: impl Future<Output = Page>,
+) {
+    let result = async {
+        command
+            .create_interaction_response(&context.http, |r| {
+                r.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                    .interaction_response_data(|d| d.ephemeral(true))
+            })
+            .await?;
+
+        let reply = reply.await;
+        command
+            .edit_original_interaction_response(&context.http, |r| {
+                r.content(reply.content).set_components(reply.buttons)
+            })
+            .await
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!(command = command.data.name, error = %e, "Unable to reply to a command");
+    }
+}
+
+// The changelog that the commands are about, or nothing if it can't be downloaded.
+async fn download(config: &DiscordConfig) -> Option<String> {
+    let url = config.changelog_url.as_deref()?;
+    changelog::fetch(&RetryConfig::default(), url)
+        .await
+        .inspect_err(|e| tracing::warn!(error = %e, "Unable to download the changelog"))
+        .ok()
+}
+
+// The number of entries in each section of the "Unreleased" section of the changelog, compared to
+// the latest release, and how long ago that was.
+async fn stats(config: &DiscordConfig, language: Option<&str>) -> String {
+    let Some(changelog) = download(config).await else {
+        return locale::text(language, "changelog-unavailable", &[]);
+    };
+
+    let unreleased = counts(&changelog::unreleased(&changelog));
+    let latest = changelog::all_releases(&changelog).into_iter().next();
+    let previous = latest
+        .as_ref()
+        .map(|r| counts(&r.notes))
+        .unwrap_or_default();
+    let titles = locale::sections(language);
+
+    let mut lines = vec![match &latest {
+        Some(r) => locale::text(language, "stats-compared", &[("version", &r.version)]),
+        None => locale::text(language, "stats-unreleased", &[]),
+    }];
+
+    // Sections of the latest release that are missing now are listed too, with no entries.
+    let mut sections = unreleased.iter().map(|(t, _)| t).collect::<Vec<_>>();
+    for (title, _) in &previous {
+        if !sections.contains(&title) {
+            sections.push(title);
+        }
+    }
+
+    let count = |counts: &[(String, usize)], title: &str| {
+        counts
+            .iter()
+            .find(|(t, _)| t == title)
+            .map_or(0, |(_, n)| *n)
+    };
+    for title in sections {
+        let section = titles.get(title).unwrap_or(title);
+        lines.push(compared(
+            language,
+            "stats-section",
+            section,
+            count(&unreleased, title),
+            latest.as_ref().map(|_| count(&previous, title)),
+        ));
+    }
+
+    let total = |counts: &[(String, usize)]| counts.iter().map(|(_, n)| n).sum::<usize>();
+    lines.push(compared(
+        language,
+        "stats-total",
+        "",
+        total(&unreleased),
+        latest.as_ref().map(|_| total(&previous)),
+    ));
+
+    let released = latest.as_ref().and_then(|r| Some((r, r.date?)));
+    if let Some((release, date)) = released {
+        let days = (chrono::Utc::now().date_naive() - date).num_days();
+        lines.push(locale::text(
+            language,
+            "stats-days",
+            &[("days", &days.to_string()), ("version", &release.version)],
+        ));
+    }
+
+    lines.join("\n")
+}
+
+// One page of the entries of the changelog that contain the query, grouped by version, newest
+// first. Pages are counted from zero.
+pub async fn search(
+    config: &DiscordConfig,
+    language: Option<&str>,
+    query: &str,
+    page: usize,
+) -> Page {
+    let Some(changelog) = download(config).await else {
+        return Page::single(locale::text(language, "changelog-unavailable", &[]));
+    };
+
+    let mut versions = vec![("Unreleased".to_string(), changelog::unreleased(&changelog))];
+    versions.extend(
+        changelog::all_releases(&changelog)
+            .into_iter()
+            .map(|r| (r.version, r.notes)),
+    );
+
+    let needle = query.to_lowercase();
+    let mut lines = vec![];
+    for (version, notes) in versions {
+        let entries = changelog::sections(&notes)
+            .into_iter()
+            .flat_map(|u| match u {
+                Update::Section { entries, .. } => entries,
+                _ => vec![],
+            })
+            .filter(|e| e.to_lowercase().contains(&needle))
+            .collect::<Vec<_>>();
+
+        if !entries.is_empty() {
+            lines.push(format!("**{}**", version));
+            lines.extend(entries);
+        }
+    }
+
+    if lines.is_empty() {
+        let reply = locale::text(language, "search-no-results", &[("query", query)]);
+        return Page::single(reply);
+    }
+
+    let pages = super::split(&lines, MAX_PAGE_LENGTH);
+    Page::of(&pages, page, "search", query, language)
+}
+
+// One page of the notes of a version, or of the unreleased changes if no version is given. Pages
+// are counted from zero.
+pub async fn changelog(
+    config: &DiscordConfig,
+    language: Option<&str>,
+    version: &str,
+    page: usize,
+) -> Page {
+    let Some(changelog) = download(config).await else {
+        return Page::single(locale::text(language, "changelog-unavailable", &[]));
+    };
+
+    // Versions are compared as versions, so `v0.15` is invalid while `v0.15.0` is the same as
+    // `0.15.0`.
+    let parsed = match version {
+        "" => None,
+        v => match changelog::parse_version(v) {
+            Some(v) => Some(v),
+            None => {
+                return Page::single(locale::text(
+                    language,
+                    "invalid-version",
+                    &[("version", version)],
+                ))
+            }
+        },
+    };
+    let release = parsed.as_ref().and_then(|v| {
+        changelog::all_releases(&changelog)
+            .into_iter()
+            .find(|r| changelog::parse_version(&r.version).as_ref() == Some(v))
+    });
+    let version = release.as_ref().map_or(version, |r| r.version.as_str());
+    let link = changelog::links(&changelog).remove(match version {
+        "" => "Unreleased",
+        v => v,
+    });
+    let notes = match &parsed {
+        None => Some(changelog::unreleased(&changelog)),
+        Some(_) => release.as_ref().map(|r| r.notes.clone()),
+    };
+    let Some(notes) = notes else {
+        return Page::single(locale::text(
+            language,
+            "unknown-version",
+            &[("version", version)],
+        ));
+    };
+
+    // Sub-sections are shown like the sections of an announcement, followed by the comparison
+    // with the previous version.
+    let mut lines = notes
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| match l.strip_prefix("### ") {
+            Some(title) => format!("**{}**", title),
+            None => l.clone(),
+        })
+        .collect::<Vec<_>>();
+    if let Some(link) = link.filter(|_| !lines.is_empty()) {
+        lines.push(format!("<{}>", link));
+    }
+    if lines.is_empty() {
+        return Page::single(locale::text(language, "no-changes", &[]));
+    }
+
+    let pages = super::split(&lines, MAX_PAGE_LENGTH);
+    Page::of(&pages, page, "changelog", version, language)
+}
+
+// Post an announcement of the ledger again in the channel: the given one, or else the latest one
+// posted in the guild.
+async fn repost(
+    context: &Context,
+    command: &ApplicationCommandInteraction,
+    guilds: &Arc<Guilds>,
+    config: &DiscordConfig,
+    retry: &RetryConfig,
+    guild_id: GuildId,
+    language: Option<&str>,
+) -> Page {
+    let id = match option(&command.data.options, "id") {
+        Some(CommandDataOptionValue::String(s)) => s.as_str(),
+        _ => "",
+    };
+    let channel = command.channel_id;
+    let ledger = guilds.get(guild_id.0).ledger;
+    let posted = ledger
+        .iter()
+        .rev()
+        .filter(|d| id.is_empty() || d.hash == id)
+        .find_map(|d| d.announcement.clone());
+    let Some(announcement) = posted else {
+        let mut ids = ledger
+            .iter()
+            .rev()
+            .map(|d| d.hash.as_str())
+            .collect::<Vec<_>>();
+        ids.dedup();
+        let reply = match ids.is_empty() {
+            true => locale::text(language, "repost-none", &[]),
+            false => locale::text(
+                language,
+                "repost-unknown",
+                &[("id", id), ("ids", &ids.join(", "))],
+            ),
+        };
+        return Page::single(reply);
+    };
+
+    let id = announcement.hash();
+    let delivery = super::repost(
+        context,
+        guilds,
+        config,
+        retry,
+        &announcement,
+        guild_id,
+        channel,
+    )
+    .await;
+    tracing::info!(guild = %guild_id, channel = %channel, id, sent = delivery.sent, "Reposted");
+
+    let key = match delivery.sent {
+        0 => "repost-failed",
+        _ => "reposted",
+    };
+    Page::single(locale::text(language, key, &[("id", &id)]))
+}
+
+fn option<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a CommandDataOptionValue> {
+    options
+        .iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.resolved.as_ref())
+}
+
+// The number of entries in each sub-section of the notes.
+fn counts(notes: &[String]) -> Vec<(String, usize)> {
+    changelog::sections(notes)
+        .into_iter()
+        .filter_map(|u| match u {
+            Update::Section { title, entries } => Some((title, entries.len())),
+            _ => None,
+        })
+        .collect()
+}
+
+// A line of the stats, with the difference to the latest release if there is one.
+fn compared(
+    language: Option<&str>,
+    key: &str,
+    section: &str,
+    count: usize,
+    previous: Option<usize>,
+) -> String {
+    let args = [("section", section), ("count", &count.to_string())];
+    match previous {
+        Some(previous) => {
+            let change = format!("{:+}", count as i64 - previous as i64);
+            let args = [args[0], args[1], ("change", &change)];
+            locale::text(language, &format!("{}-compared", key), &args)
+        }
+        None => locale::text(language, key, &args),
+    }
+}
+
+async fn setup_ping_role(
+    context: &Context,
+    guilds: &Guilds,
+    guild_id: GuildId,
+    option: &CommandDataOption,
+    language: Option<&str>,
+) -> String {
+    let role = option.options.iter().find_map(|o| match &o.resolved {
+        Some(CommandDataOptionValue::Role(r)) => Some(r.id),
+        _ => None,
+    });
+
+    guilds.update(guild_id.0, |g| g.ping_role = role.map(|r| r.0));
+    tracing::info!(guild = %guild_id, role = ?role, "Set the ping role");
+    roles::update(context, guilds, guild_id, role, language).await;
+
+    match role {
+        Some(r) => locale::text(
+            language,
+            "ping-role-set",
+            &[("role", &r.mention().to_string())],
+        ),
+        None => locale::text(language, "ping-role-unset", &[]),
+    }
+}
+
+// Post the role menu in the channel that the command was used in.
+async fn setup_role_menu(
+    context: &Context,
+    command: &ApplicationCommandInteraction,
+    guilds: &Guilds,
+    guild_id: GuildId,
+    language: Option<&str>,
+) -> String {
+    let Some(role) = guilds.get(guild_id.0).ping_role.map(RoleId) else {
+        return locale::text(language, "role-menu-no-role", &[]);
+    };
+
+    match roles::post(
+        context,
+        guilds,
+        guild_id,
+        command.channel_id,
+        role,
+        language,
+    )
+    .await
+    {
+        Ok(()) => {
+            tracing::info!(guild = %guild_id, channel = %command.channel_id, "Posted the role menu");
+            locale::text(language, "role-menu-posted", &[])
+        }
+        Err(e) => {
+            tracing::warn!(guild = %guild_id, error = %e, "Unable to post the role menu");
+            locale::text(language, "role-menu-unavailable", &[])
+        }
+    }
+}
+
+// Choose the language of the announcements, which the reply is already in.
+fn setup_language(guilds: &Guilds, guild_id: GuildId, option: &CommandDataOption) -> String {
+    let language = option.options.iter().find_map(|o| match &o.resolved {
+        Some(CommandDataOptionValue::String(s)) => Some(s.as_str()),
+        _ => None,
+    });
+    let Some(language) = language.filter(|l| locale::exists(l)) else {
+        let language = language.unwrap_or_default();
+        return locale::text(None, "unknown-language", &[("language", language)]);
+    };
+
+    guilds.update(guild_id.0, |g| g.language = Some(language.to_string()));
+    tracing::info!(guild = %guild_id, language, "Set the language");
+
+    let name = locale::languages()
+        .into_iter()
+        .find(|(code, _)| *code == language)
+        .map_or(language, |(_, name)| name);
+    locale::text(Some(language), "language-set", &[("language", name)])
+}
+
+// Subscribe or unsubscribe whoever used the command to the announcements in direct messages,
+// which can be done from anywhere. The announcements and the reply are in the language of their
+// Discord client, if there are strings for it.
+fn subscribe(command: &ApplicationCommandInteraction, guilds: &Guilds) -> String {
+    let user = command.user.id;
+    let language = command
+        .locale
+        .split('-')
+        .next()
+        .filter(|l| locale::exists(l));
+
+    let (changed, key) = match command.data.name.as_str() {
+        "subscribe" => (
+            guilds.subscribe(user.0, language.map(|l| l.to_string())),
+            "subscribed",
+        ),
+        _ => (guilds.unsubscribe(user.0), "unsubscribed"),
+    };
+    tracing::info!(user = %user, command = command.data.name, changed, "Set the subscription");
+
+    match changed {
+        true => locale::text(language, key, &[]),
+        false => locale::text(language, &format!("already-{}", key), &[]),
+    }
+}
+
+fn setup_style(
+    guilds: &Guilds,
+    guild_id: GuildId,
+    option: &CommandDataOption,
+    language: Option<&str>,
+) -> String {
+    let style = option.options.iter().find_map(|o| match &o.resolved {
+        Some(CommandDataOptionValue::String(s)) => Some(s.as_str()),
+        _ => None,
+    });
+    let (style, key) = match style {
+        Some("compact") => (Style::Compact, "style-compact"),
+        Some("digest") => (Style::Digest, "style-digest"),
+        _ => (Style::Full, "style-full"),
+    };
+
+    guilds.update(guild_id.0, |g| g.style = style);
+    tracing::info!(guild = %guild_id, style = key, "Set the style");
+
+    locale::text(language, key, &[])
+}
+
+fn set_paused(guilds: &Guilds, guild_id: GuildId, paused: bool, language: Option<&str>) -> String {
+    guilds.update(guild_id.0, |g| g.paused = paused);
+    tracing::info!(guild = %guild_id, paused, "Set whether announcements are paused");
+
+    match paused {
+        true => locale::text(language, "paused", &[]),
+        false => locale::text(language, "resumed", &[]),
+    }
+}
@@ -0,0 +1,66 @@
+use serenity::http::Http;
+use serenity::model::guild::ScheduledEventType;
+use serenity::model::id::GuildId;
+use serenity::model::Timestamp;
+
+use crate::announcement::Announcement;
+use crate::config::EventsConfig;
+use crate::locale;
+
+// Limits of scheduled events, in characters.
+const MAX_NAME_LENGTH: usize = 100;
+const MAX_DESCRIPTION_LENGTH: usize = 1000;
+
+// Create a scheduled event in the guild for every release of the announcement and every milestone
+// that it completes, with the release notes as the description. The announcement has already been
+// posted, so an event that can't be created is only logged.
+pub async fn create(
+    http: &Http,
+    config: &EventsConfig,
+    announcement: &Announcement,
+    guild_id: GuildId,
+    language: Option<&str>,
+) {
+    let project = &announcement.name;
+    let releases = announcement
+        .releases
+        .iter()
+        .map(|r| (r.version.as_str(), r.notes.join("\n")));
+    let milestones = announcement.completed.iter().map(|m| {
+        let description = locale::text(language, "event-milestone", &[("milestone", m)]);
+        (m.as_str(), description)
+    });
+
+    let start = chrono::Utc::now().timestamp() + config.starts_after_minutes as i64 * 60;
+    let end = start + config.duration_minutes.max(1) as i64 * 60;
+    for (version, description) in releases.chain(milestones) {
+        let name = locale::text(
+            language,
+            "event-name",
+            &[("project", project), ("version", version)],
+        );
+
+        let result = guild_id
+            .create_scheduled_event(http, |e| {
+                e.name(truncate(&name, MAX_NAME_LENGTH))
+                    .description(truncate(&description, MAX_DESCRIPTION_LENGTH))
+                    .kind(ScheduledEventType::External)
+                    .location(&config.location)
+                    .start_time(Timestamp::from_unix_timestamp(start).unwrap())
+                    .end_time(Timestamp::from_unix_timestamp(end).unwrap())
+            })
+            .await;
+        match result {
+            Ok(event) => tracing::info!(guild = %guild_id, event = %event.id, "Created an event"),
+            Err(e) => tracing::warn!(guild = %guild_id, error = %e, "Unable to create an event"),
+        }
+    }
+}
+
+// Truncate the text to at most `length` characters, ending it with an ellipsis if it was cut.
+fn truncate(text: &str, length: usize) -> String {
+    match text.chars().count() > length {
+        true => text.chars().take(length - 1).collect::<String>() + "…",
+        false => text.to_string(),
+    }
+}
@@ -0,0 +1,46 @@
+use regex::Regex;
+
+// Characters that Discord reads as formatting: bold and italics, underline, strikethrough, spoilers,
+// code, and the escape itself.
+const SPECIAL: &[char] = &['\\', '*', '_', '~', '|', '`'];
+
+// Escape an entry of the changelog, so that Discord shows it as written. Code spans, the targets of
+// links and bare URLs are kept as they are, since they are meant to be formatted, and escaping
+// would break the URLs. Mentions are broken up with a zero-width space, so that they don't even
+// look like pings.
+pub fn escape(entry: &str) -> String {
+    let kept = Regex::new(r"`[^`]+`|\[([^\]]*)\]\(([^)\s]+)\)|https?://[^\s)>]+").unwrap();
+
+    let mut escaped = String::new();
+    let mut rest = 0;
+    for capture in kept.captures_iter(entry) {
+        let whole = capture.get(0).unwrap();
+        escaped.push_str(&escape_text(&entry[rest..whole.start()]));
+        match (capture.get(1), capture.get(2)) {
+            (Some(text), Some(url)) => escaped.push_str(&format!(
+                "[{}]({})",
+                escape_text(text.as_str()),
+                url.as_str()
+            )),
+            _ => escaped.push_str(whole.as_str()),
+        }
+        rest = whole.end();
+    }
+    escaped.push_str(&escape_text(&entry[rest..]));
+
+    escaped
+}
+
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if SPECIAL.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        if c == '@' {
+            escaped.push('\u{200b}');
+        }
+    }
+    escaped
+}
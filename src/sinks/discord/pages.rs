@@ -0,0 +1,166 @@
+use serenity::builder::CreateComponents;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::model::channel::MessageFlags;
+use serenity::prelude::*;
+
+use crate::config::DiscordConfig;
+use crate::guilds::Guilds;
+use crate::locale;
+use crate::sinks::discord::commands;
+use crate::sinks::discord::MAX_LENGTH;
+
+// Leaves room for the page number, and the roles mentioned by an announcement.
+pub const MAX_PAGE_LENGTH: usize = MAX_LENGTH - 100;
+
+// Number of paginated announcements remembered in each guild, after which the oldest can no longer
+// be browsed.
+pub const MAX_PAGED: usize = 20;
+
+// One page of something that is browsed with the previous and next buttons. The buttons say what
+// they show in their ID, as `page:<kind>:<page>:<argument>`, so that nothing but the pages of the
+// announcements needs to be remembered.
+pub struct Page {
+    pub content: String,
+    pub buttons: CreateComponents,
+}
+
+impl Page {
+    // A reply that fits into a single message.
+    pub fn single(content: String) -> Page {
+        Page {
+            content,
+            buttons: CreateComponents::default(),
+        }
+    }
+
+    // The page with the given index, where `kind` and `argument` are what the buttons find the
+    // pages with again.
+    pub fn of(
+        pages: &[String],
+        page: usize,
+        kind: &str,
+        argument: &str,
+        language: Option<&str>,
+    ) -> Page {
+        if pages.len() < 2 {
+            return Page::single(pages.first().cloned().unwrap_or_default());
+        }
+
+        let page = page.min(pages.len() - 1);
+        let footer = locale::text(
+            language,
+            "page",
+            &[
+                ("page", &(page + 1).to_string()),
+                ("pages", &pages.len().to_string()),
+            ],
+        );
+
+        let id = |page: usize| format!("page:{}:{}:{}", kind, page, argument);
+        let mut buttons = CreateComponents::default();
+        buttons.create_action_row(|r| {
+            r.create_button(|b| {
+                b.custom_id(id(page.saturating_sub(1)))
+                    .label(locale::text(language, "previous", &[]))
+                    .style(ButtonStyle::Secondary)
+                    .disabled(page == 0)
+            })
+            .create_button(|b| {
+                b.custom_id(id(page + 1))
+                    .label(locale::text(language, "next", &[]))
+                    .style(ButtonStyle::Secondary)
+                    .disabled(page + 1 == pages.len())
+            })
+        });
+
+        Page {
+            content: format!("{}\n\n{}", pages[page], footer),
+            buttons,
+        }
+    }
+}
+
+// Show the page of a button that was clicked. The pages of an announcement are shown to whoever
+// clicked in a message that only they see, where they can keep browsing, while the pages of a
+// command's reply replace the reply.
+pub async fn handle(
+    context: &Context,
+    component: &MessageComponentInteraction,
+    guilds: &Guilds,
+    config: &DiscordConfig,
+    language: Option<&str>,
+) {
+    let mut parts = component.data.custom_id.splitn(4, ':');
+    let (Some("page"), Some(kind), Some(page), Some(argument)) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return;
+    };
+    let page = page.parse::<usize>().unwrap_or_default();
+    let ephemeral = component
+        .message
+        .flags
+        .is_some_and(|f| f.contains(MessageFlags::EPHEMERAL));
+
+    let result = match kind {
+        "announcement" => {
+            // The buttons under the announcement itself don't know its ID, since they are created
+            // before it's posted.
+            let message = match argument {
+                "" => component.message.id.0,
+                id => id.parse().unwrap_or_default(),
+            };
+            let pages = component
+                .guild_id
+                .and_then(|g| guilds.get(g.0).pages.get(&message).cloned())
+                .unwrap_or_else(|| vec![locale::text(language, "pages-gone", &[])]);
+            let page = Page::of(&pages, page, kind, &message.to_string(), language);
+
+            let kind = match ephemeral {
+                true => InteractionResponseType::UpdateMessage,
+                false => InteractionResponseType::ChannelMessageWithSource,
+            };
+            component
+                .create_interaction_response(&context.http, |r| {
+                    r.kind(kind).interaction_response_data(|d| {
+                        d.content(page.content)
+                            .set_components(page.buttons)
+                            .allowed_mentions(|a| a.empty_parse())
+                            .ephemeral(true)
+                    })
+                })
+                .await
+        }
+        "search" | "changelog" => {
+            // The changelog is downloaded again, which can take longer than Discord waits for.
+            let deferred = component
+                .create_interaction_response(&context.http, |r| {
+                    r.kind(InteractionResponseType::DeferredUpdateMessage)
+                })
+                .await;
+
+            match deferred {
+                Ok(()) => {
+                    let page = match kind {
+                        "search" => commands::search(config, language, argument, page).await,
+                        _ => commands::changelog(config, language, argument, page).await,
+                    };
+                    component
+                        .edit_original_interaction_response(&context.http, |r| {
+                            r.content(page.content).set_components(page.buttons)
+                        })
+                        .await
+                        .map(|_| ())
+                }
+                Err(e) => Err(e),
+            }
+        }
+        _ => return,
+    };
+
+    if let Err(e) = result {
+        tracing::warn!(button = component.data.custom_id, error = %e, "Unable to show the page");
+    }
+}
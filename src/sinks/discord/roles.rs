@@ -0,0 +1,164 @@
+use serenity::builder::CreateComponents;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::model::id::ChannelId;
+use serenity::model::id::GuildId;
+use serenity::model::id::MessageId;
+use serenity::model::id::RoleId;
+use serenity::model::mention::Mentionable;
+use serenity::prelude::*;
+
+use crate::guilds::Guilds;
+use crate::guilds::RoleMenu;
+use crate::locale;
+
+// ID of the button of the role menu. The role isn't part of it, so that changing the ping role
+// doesn't break the buttons of the menu that is already posted.
+const TOGGLE_ID: &str = "role:toggle";
+
+// Post the role menu in the channel, replacing the guild's previous menu.
+pub async fn post(
+    context: &Context,
+    guilds: &Guilds,
+    guild_id: GuildId,
+    channel: ChannelId,
+    role: RoleId,
+    language: Option<&str>,
+) -> serenity::Result<()> {
+    remove(context, guilds, guild_id).await;
+
+    let message = channel
+        .send_message(&context.http, |m| {
+            m.content(text(role, language))
+                .set_components(buttons(language))
+                .allowed_mentions(|a| a.empty_parse())
+        })
+        .await?;
+
+    let menu = RoleMenu {
+        channel: channel.0,
+        message: message.id.0,
+    };
+    guilds.update(guild_id.0, |g| g.role_menu = Some(menu));
+    Ok(())
+}
+
+// Show the new ping role in the guild's menu, or remove the menu if there is no ping role anymore.
+// The role has been changed either way, so a menu that can't be edited is only logged.
+pub async fn update(
+    context: &Context,
+    guilds: &Guilds,
+    guild_id: GuildId,
+    role: Option<RoleId>,
+    language: Option<&str>,
+) {
+    let Some(role) = role else {
+        return remove(context, guilds, guild_id).await;
+    };
+    let Some(menu) = guilds.get(guild_id.0).role_menu else {
+        return;
+    };
+
+    let result = ChannelId(menu.channel)
+        .edit_message(&context.http, MessageId(menu.message), |m| {
+            m.content(text(role, language))
+        })
+        .await;
+    if let Err(e) = result {
+        tracing::warn!(guild = %guild_id, error = %e, "Unable to update the role menu");
+    }
+}
+
+// Delete the guild's menu, if it has one. Menus that were already deleted are only forgotten.
+async fn remove(context: &Context, guilds: &Guilds, guild_id: GuildId) {
+    let Some(menu) = guilds.get(guild_id.0).role_menu else {
+        return;
+    };
+    guilds.update(guild_id.0, |g| g.role_menu = None);
+
+    let result = ChannelId(menu.channel)
+        .delete_message(&context.http, MessageId(menu.message))
+        .await;
+    if let Err(e) = result {
+        tracing::debug!(guild = %guild_id, error = %e, "Unable to delete the old role menu");
+    }
+}
+
+// Give the ping role to whoever clicked the button of the menu, or take it away if they have it,
+// replying with what was done in a message that only they see.
+pub async fn handle(
+    context: &Context,
+    component: &MessageComponentInteraction,
+    guilds: &Guilds,
+    language: Option<&str>,
+) {
+    if component.data.custom_id != TOGGLE_ID {
+        return;
+    }
+    let (Some(guild_id), Some(member)) = (component.guild_id, &component.member) else {
+        return;
+    };
+
+    let reply = match guilds.get(guild_id.0).ping_role.map(RoleId) {
+        None => locale::text(language, "role-menu-no-role", &[]),
+        Some(role) => {
+            let mention = role.mention().to_string();
+            let user = member.user.id.0;
+            let result = match member.roles.contains(&role) {
+                true => context
+                    .http
+                    .remove_member_role(guild_id.0, user, role.0, None)
+                    .await
+                    .map(|()| "role-removed"),
+                false => context
+                    .http
+                    .add_member_role(guild_id.0, user, role.0, None)
+                    .await
+                    .map(|()| "role-added"),
+            };
+
+            match result {
+                Ok(key) => locale::text(language, key, &[("role", &mention)]),
+                Err(e) => {
+                    tracing::warn!(guild = %guild_id, error = %e, "Unable to toggle the ping role");
+                    locale::text(language, "role-unavailable", &[("role", &mention)])
+                }
+            }
+        }
+    };
+
+    let result = component
+        .create_interaction_response(&context.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.content(reply)
+                        .allowed_mentions(|a| a.empty_parse())
+                        .ephemeral(true)
+                })
+        })
+        .await;
+    if let Err(e) = result {
+        tracing::warn!(error = %e, "Unable to reply to the role menu");
+    }
+}
+
+fn text(role: RoleId, language: Option<&str>) -> String {
+    locale::text(
+        language,
+        "role-menu",
+        &[("role", &role.mention().to_string())],
+    )
+}
+
+fn buttons(language: Option<&str>) -> CreateComponents {
+    let mut buttons = CreateComponents::default();
+    buttons.create_action_row(|r| {
+        r.create_button(|b| {
+            b.custom_id(TOGGLE_ID)
+                .label(locale::text(language, "role-menu-button", &[]))
+                .style(ButtonStyle::Primary)
+        })
+    });
+    buttons
+}
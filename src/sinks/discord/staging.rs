@@ -0,0 +1,198 @@
+use serenity::builder::CreateComponents;
+use serenity::http::Http;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::model::id::ChannelId;
+use serenity::model::id::MessageId;
+use serenity::model::id::RoleId;
+use serenity::model::mention::Mentionable;
+use serenity::model::permissions::Permissions;
+use serenity::prelude::*;
+use tokio::sync::Notify;
+
+use crate::announcement::Announcement;
+use crate::config::StagingConfig;
+use crate::guilds::Guilds;
+use crate::locale;
+use crate::sinks::discord::split;
+use crate::sinks::discord::MAX_LENGTH;
+
+const APPROVE_ID: &str = "staging:approve";
+const REJECT_ID: &str = "staging:reject";
+
+// Post the announcement to the staging channel with buttons to approve or reject it, returning the
+// message with the buttons. The review is remembered by the caller, since it outlives the check.
+pub async fn post(
+    http: &Http,
+    config: &StagingConfig,
+    announcement: &Announcement,
+) -> serenity::Result<MessageId> {
+    let channel = ChannelId(config.channel);
+    let lines = announcement
+        .message()
+        .lines()
+        .map(|l| l.to_string())
+        .collect::<Vec<_>>();
+    for page in split(&lines, MAX_LENGTH) {
+        channel
+            .send_message(http, |m| {
+                m.content(page).allowed_mentions(|a| a.empty_parse())
+            })
+            .await?;
+    }
+    let message = channel
+        .send_message(http, |m| {
+            m.content(locale::text(None, "review-prompt", &[]))
+                .components(|c| buttons(c, false))
+        })
+        .await?;
+
+    tracing::info!(channel = %channel, "Posted the announcement for review");
+    Ok(message.id)
+}
+
+// Note that nobody reviewed the announcement of the message in time.
+pub async fn expire(http: &Http, config: &StagingConfig, message: MessageId) {
+    let channel = ChannelId(config.channel);
+    let result = channel
+        .send_message(http, |m| {
+            m.content(locale::text(None, "review-expired", &[]))
+                .reference_message((channel, message))
+                .allowed_mentions(|a| a.empty_parse())
+        })
+        .await;
+    if let Err(e) = result {
+        tracing::debug!(error = %e, "Unable to note that the review expired");
+    }
+    disable(http, channel, message).await;
+}
+
+// Approve or reject the announcement whose button was pressed, if the user may review it. An
+// approved announcement is posted by the next check, which is requested right away if possible.
+pub async fn handle(
+    context: &Context,
+    component: &MessageComponentInteraction,
+    guilds: &Guilds,
+    config: &StagingConfig,
+    requested: Option<&Notify>,
+    language: Option<&str>,
+) {
+    let approved = match component.data.custom_id.as_str() {
+        APPROVE_ID => true,
+        REJECT_ID => false,
+        _ => return,
+    };
+
+    let user = &component.user;
+    if !allowed(component, config) {
+        let reply = locale::text(language, "review-not-allowed", &[]);
+        return respond(context, component, reply).await;
+    }
+
+    let message = component.message.id.0;
+    let decided = guilds.update_reviews(|r| match r.pending.get_mut(&message) {
+        Some(review) if !review.approved => {
+            match approved {
+                true => review.approved = true,
+                false => r.reject(message),
+            }
+            true
+        }
+        _ => false,
+    });
+    if !decided {
+        let reply = locale::text(language, "review-stale", &[]);
+        return respond(context, component, reply).await;
+    }
+    if let Some(requested) = requested.filter(|_| approved) {
+        requested.notify_one();
+    }
+
+    let key = match approved {
+        true => "review-approved",
+        false => "review-rejected",
+    };
+    tracing::info!(user = %user.id, approved, "Reviewed the announcement");
+    let reply = locale::text(language, key, &[("user", &user.mention().to_string())]);
+    let result = component
+        .create_interaction_response(&context.http, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| {
+                    let mut components = CreateComponents::default();
+                    buttons(&mut components, true);
+                    d.set_components(components)
+                })
+        })
+        .await;
+    if let Err(e) = result {
+        tracing::warn!(error = %e, "Unable to disable the review buttons");
+    }
+
+    let result = component
+        .create_followup_message(&context.http, |m| {
+            m.content(reply).allowed_mentions(|a| a.empty_parse())
+        })
+        .await;
+    if let Err(e) = result {
+        tracing::warn!(error = %e, "Unable to reply to the review");
+    }
+}
+
+// Reviewers are the configured users and the members of the configured roles, or whoever can
+// manage the guild if none are configured.
+fn allowed(component: &MessageComponentInteraction, config: &StagingConfig) -> bool {
+    let Some(member) = &component.member else {
+        return false;
+    };
+    if config.reviewers.is_empty() && config.roles.is_empty() {
+        return member
+            .permissions
+            .is_some_and(|p| p.contains(Permissions::MANAGE_GUILD));
+    }
+
+    config.reviewers.contains(&member.user.id.0)
+        || config
+            .roles
+            .iter()
+            .any(|r| member.roles.contains(&RoleId(*r)))
+}
+
+async fn respond(context: &Context, component: &MessageComponentInteraction, reply: String) {
+    let result = component
+        .create_interaction_response(&context.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(reply).ephemeral(true))
+        })
+        .await;
+    if let Err(e) = result {
+        tracing::warn!(error = %e, "Unable to reply to the review");
+    }
+}
+
+// Leave the buttons, but greyed out, so that the message still shows what it was for.
+async fn disable(http: &Http, channel: ChannelId, message: MessageId) {
+    let result = channel
+        .edit_message(http, message, |m| m.components(|c| buttons(c, true)))
+        .await;
+    if let Err(e) = result {
+        tracing::debug!(error = %e, "Unable to disable the review buttons");
+    }
+}
+
+fn buttons(components: &mut CreateComponents, disabled: bool) -> &mut CreateComponents {
+    components.create_action_row(|r| {
+        r.create_button(|b| {
+            b.custom_id(APPROVE_ID)
+                .label(locale::text(None, "review-approve", &[]))
+                .style(ButtonStyle::Success)
+                .disabled(disabled)
+        })
+        .create_button(|b| {
+            b.custom_id(REJECT_ID)
+                .label(locale::text(None, "review-reject", &[]))
+                .style(ButtonStyle::Danger)
+                .disabled(disabled)
+        })
+    })
+}
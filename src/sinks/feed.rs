@@ -0,0 +1,154 @@
+use std::fs;
+use std::io;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use atom_syndication::Content;
+use atom_syndication::Entry;
+use atom_syndication::Feed;
+use atom_syndication::Link;
+use atom_syndication::Text;
+
+use crate::announcement::Announcement;
+use crate::config::ProjectConfig;
+use crate::html;
+use crate::sinks::news_title;
+use crate::sinks::outbox::Posting;
+use crate::sinks::outbox::Queued;
+use crate::sinks::Delivery;
+use crate::sinks::Outbox;
+use crate::sinks::Sink;
+use crate::state;
+
+const FEED_PATH: &str = "ANNOUNCEMENTS.xml";
+const FEED_ID: &str = "tag:veloren-changelog,2023:announcements";
+const VELOREN_LINK: &str = "https://veloren.net/";
+
+// Maximum number of announcements kept in the feed.
+const MAX_ENTRIES: usize = 50;
+
+// Records every announcement in an Atom feed for those who don't use Discord.
+pub struct FeedSink {
+    path: PathBuf,
+    title: String,
+    // The website of the project, which is only known for Veloren.
+    link: Option<&'static str>,
+    outbox: Arc<Outbox>,
+}
+
+impl FeedSink {
+    // The feed is kept in the state directory.
+    pub fn new(dir: &Path, project: &ProjectConfig, outbox: Arc<Outbox>) -> FeedSink {
+        FeedSink {
+            path: dir.join(FEED_PATH),
+            title: news_title(project.name()),
+            link: project.is_veloren().then_some(VELOREN_LINK),
+            outbox,
+        }
+    }
+
+    // Add the announcement to the feed, unless a run that didn't get to save the state already
+    // added it.
+    fn post(&self, announcement: &Announcement, queued: Option<&Queued>) -> Delivery {
+        let mut posting = Posting::new(&self.outbox, self.name(), announcement, queued);
+        if posting.done(FEED_PATH) {
+            return posting.delivery;
+        }
+
+        let posted_at = posting.posted_at();
+        let result = append(&self.path, &self.title, self.link, announcement, posted_at);
+        if let Err(e) = &result {
+            tracing::warn!(file = %self.path.display(), error = %e, "Unable to write the announcement feed");
+        }
+        let target = format!("Feed in {}", self.path.display());
+        posting.record(FEED_PATH, target, &result);
+        posting.delivery
+    }
+}
+
+#[async_trait]
+impl Sink for FeedSink {
+    fn name(&self) -> &'static str {
+        "feed"
+    }
+
+    async fn publish(&self, announcement: &Announcement) -> Delivery {
+        self.post(announcement, None)
+    }
+
+    async fn redeliver(&self) -> Delivery {
+        let mut delivery = Delivery::default();
+        let paths = [FEED_PATH.to_string()];
+        for queued in self.outbox.take(self.name(), &paths) {
+            delivery.merge(self.post(&queued.announcement, Some(&queued)));
+        }
+        delivery
+    }
+}
+
+// Add an announcement to the feed file, creating the file if it doesn't exist yet. The feed file
+// itself is the only record of past announcements. The entry is identified by the hash of the
+// announcement and dated by when it was first posted, so posting it again doesn't change either.
+// The title and link of the feed follow the config.
+fn append(
+    path: &Path,
+    title: &str,
+    link: Option<&str>,
+    announcement: &Announcement,
+    posted_at: i64,
+) -> io::Result<()> {
+    let mut feed = match fs::File::open(path) {
+        Ok(f) => Feed::read_from(BufReader::new(f)).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Unable to parse the announcement feed, starting a new one");
+            new_feed()
+        }),
+        Err(_) => new_feed(),
+    };
+
+    let id = format!("{}:{}", FEED_ID, announcement.hash());
+    if feed.entries.iter().any(|e| e.id == id) {
+        return Ok(());
+    }
+    let date = chrono::DateTime::from_timestamp(posted_at, 0)
+        .unwrap_or_else(chrono::Utc::now)
+        .fixed_offset();
+
+    let mut entry = Entry::default();
+    entry.set_id(id);
+    entry.set_title(Text::plain(format!(
+        "{} ({})",
+        news_title(&announcement.name),
+        date.format("%Y-%m-%d")
+    )));
+    entry.set_updated(date);
+    entry.set_published(date);
+    entry.set_content(Content {
+        value: Some(html::render(&announcement.changes, &announcement.devblogs)),
+        content_type: Some("html".to_string()),
+        ..Default::default()
+    });
+
+    feed.set_title(title);
+    let links = link.map(|href| Link {
+        href: href.to_string(),
+        rel: "alternate".to_string(),
+        ..Default::default()
+    });
+    feed.set_links(links.into_iter().collect::<Vec<_>>());
+    feed.set_updated(chrono::Utc::now().fixed_offset());
+    feed.entries.insert(0, entry);
+    feed.entries.truncate(MAX_ENTRIES);
+
+    let mut contents = vec![];
+    feed.write_to(&mut contents).map_err(io::Error::other)?;
+    state::try_write_atomic(path, contents)
+}
+
+fn new_feed() -> Feed {
+    let mut feed = Feed::default();
+    feed.set_id(FEED_ID);
+    feed
+}
@@ -0,0 +1,230 @@
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::net::TcpStream;
+
+use crate::announcement::Announcement;
+use crate::config::IrcConfig;
+use crate::sinks::outbox::Posting;
+use crate::sinks::outbox::Progress;
+use crate::sinks::outbox::Queued;
+use crate::sinks::Delivery;
+use crate::sinks::Outbox;
+use crate::sinks::Sink;
+
+// Maximum length of the text of a single PRIVMSG. The protocol limits a whole line to 512 bytes,
+// which includes the command, the target, and the prefix added by the server.
+const MAX_LENGTH: usize = 400;
+
+// Delay between messages so the server doesn't disconnect the bot for flooding.
+const MESSAGE_DELAY: Duration = Duration::from_millis(500);
+
+// Time after which the whole session is abandoned.
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+pub struct IrcSink {
+    config: IrcConfig,
+    outbox: Arc<Outbox>,
+}
+
+impl IrcSink {
+    pub fn new(config: IrcConfig, outbox: Arc<Outbox>) -> IrcSink {
+        IrcSink { config, outbox }
+    }
+
+    // Connect to the server, post the announcement to the channels, and disconnect. It is queued
+    // for the channels that it wasn't fully posted to before the session failed, and a queued
+    // announcement is resumed with the first line that wasn't posted.
+    async fn post(
+        &self,
+        announcement: &Announcement,
+        channels: &[String],
+        queued: Option<&Queued>,
+    ) -> Delivery {
+        let mut posting = Posting::new(&self.outbox, self.name(), announcement, queued);
+        let mut channels = channels
+            .iter()
+            .filter(|c| !posting.done(c))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|c| (c, posting.progress(c)))
+            .collect::<Vec<_>>();
+        if channels.is_empty() {
+            return posting.delivery;
+        }
+
+        let lines = render(&self.config, announcement);
+        let progress = &mut channels;
+
+        let result = tokio::time::timeout(TIMEOUT, async {
+            let stream =
+                TcpStream::connect((self.config.server.as_str(), self.config.port)).await?;
+            if self.config.tls {
+                let connector =
+                    tokio_native_tls::native_tls::TlsConnector::new().map_err(io::Error::other)?;
+                let stream = tokio_native_tls::TlsConnector::from(connector)
+                    .connect(&self.config.server, stream)
+                    .await
+                    .map_err(io::Error::other)?;
+                session(stream, &self.config, progress, &lines).await
+            } else {
+                session(stream, &self.config, progress, &lines).await
+            }
+        })
+        .await;
+
+        match &result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                tracing::warn!(server = self.config.server, error = %e, "IRC server cannot be written to")
+            }
+            Err(_) => tracing::warn!(server = self.config.server, "IRC server timed out"),
+        }
+
+        for (channel, progress) in channels {
+            let result = match progress.sent >= lines.len() {
+                true => Ok(()),
+                false => Err(()),
+            };
+            let target = format!("IRC channel {} on {}", channel, self.config.server);
+            posting.record_progress(channel, target, progress, &result);
+        }
+        posting.delivery
+    }
+}
+
+#[async_trait]
+impl Sink for IrcSink {
+    fn name(&self) -> &'static str {
+        "irc"
+    }
+
+    async fn publish(&self, announcement: &Announcement) -> Delivery {
+        self.post(announcement, &self.config.channels, None).await
+    }
+
+    async fn redeliver(&self) -> Delivery {
+        let mut delivery = Delivery::default();
+        for queued in self.outbox.take(self.name(), &self.config.channels) {
+            let channels = [queued.target.clone()];
+            let channel_delivery = self.post(&queued.announcement, &channels, Some(&queued));
+            delivery.merge(channel_delivery.await);
+        }
+        delivery
+    }
+}
+
+// Post the lines to every channel, counting the lines that were posted to each channel in its
+// progress.
+async fn session<S>(
+    stream: S,
+    config: &IrcConfig,
+    channels: &mut [(&String, Progress)],
+    lines: &[String],
+) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader).lines();
+
+    if let Some(password) = &config.password {
+        send(&mut writer, &format!("PASS {}", password)).await?;
+    }
+    send(&mut writer, &format!("NICK {}", config.nickname)).await?;
+    send(
+        &mut writer,
+        &format!("USER {} 0 * :Veloren changelog bot", config.nickname),
+    )
+    .await?;
+
+    // Wait for the welcome message before joining, answering pings in the meantime.
+    loop {
+        let Some(line) = reader.next_line().await? else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+
+        if let Some(s) = line.strip_prefix("PING") {
+            send(&mut writer, &format!("PONG{}", s)).await?;
+        } else if line.split(' ').nth(1) == Some("001") {
+            break;
+        } else if line.starts_with("ERROR") {
+            return Err(io::Error::other(line));
+        }
+    }
+
+    for (channel, progress) in channels {
+        send(&mut writer, &format!("JOIN {}", channel)).await?;
+        for line in lines.iter().skip(progress.sent) {
+            send(&mut writer, &format!("PRIVMSG {} :{}", channel, line)).await?;
+            progress.sent += 1;
+            tokio::time::sleep(MESSAGE_DELAY).await;
+        }
+    }
+
+    send(&mut writer, "QUIT :Done").await?;
+    writer.flush().await
+}
+
+async fn send<W: AsyncWrite + Unpin>(writer: &mut W, line: &str) -> io::Result<()> {
+    writer.write_all(line.as_bytes()).await?;
+    writer.write_all(b"\r\n").await
+}
+
+// Render the announcement as plain lines. Section headers are made bold, long lines are wrapped,
+// and the announcement is cut off after the configured number of lines.
+fn render(config: &IrcConfig, announcement: &Announcement) -> Vec<String> {
+    let mut lines = vec![format!("\x02{}\x02", announcement.heading())];
+
+    for line in &announcement.changes {
+        match line.strip_prefix("## ") {
+            Some(s) => lines.push(format!("\x02{}\x02", s)),
+            None => lines.extend(line.lines().flat_map(wrap)),
+        }
+    }
+
+    for d in &announcement.devblogs {
+        lines.push(format!("- {} {}", d.title, d.url));
+    }
+
+    if lines.len() > config.max_lines {
+        let more = lines.len() - config.max_lines + 1;
+        lines.truncate(config.max_lines.saturating_sub(1));
+        lines.push(format!("...and {} more lines: {}", more, config.link));
+    }
+
+    lines
+}
+
+// Wrap a line at word boundaries so that every piece fits into a single message.
+fn wrap(line: &str) -> Vec<String> {
+    let mut pieces: Vec<String> = vec![];
+
+    for word in line.split_whitespace() {
+        match pieces.last_mut() {
+            Some(last) if last.len() + word.len() < MAX_LENGTH => {
+                last.push(' ');
+                last.push_str(word);
+            }
+            _ => pieces.push(truncate(word, MAX_LENGTH)),
+        }
+    }
+
+    pieces
+}
+
+// Truncate a string to at most `length` bytes without splitting a character.
+fn truncate(s: &str, length: usize) -> String {
+    let mut end = s.len().min(length);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s[..end].to_string()
+}
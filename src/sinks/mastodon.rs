@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::announcement::Announcement;
+use crate::config::MastodonConfig;
+use crate::http;
+use crate::sinks::outbox::Posting;
+use crate::sinks::outbox::Queued;
+use crate::sinks::Delivery;
+use crate::sinks::Outbox;
+use crate::sinks::Sink;
+
+#[derive(Deserialize)]
+struct Status {
+    id: String,
+}
+
+pub struct MastodonSink {
+    config: MastodonConfig,
+    outbox: Arc<Outbox>,
+}
+
+impl MastodonSink {
+    pub fn new(config: MastodonConfig, outbox: Arc<Outbox>) -> MastodonSink {
+        MastodonSink { config, outbox }
+    }
+
+    // Post a short summary of the announcement, followed by the full announcement as a thread of
+    // replies if enabled. The account is the only target, which is identified by its instance. A
+    // queued announcement is resumed with the first status that wasn't posted, replying to the
+    // last one that was.
+    async fn post(&self, announcement: &Announcement, queued: Option<&Queued>) -> Delivery {
+        let instance = &self.config.instance;
+        let mut posting = Posting::new(&self.outbox, self.name(), announcement, queued);
+        if posting.done(instance) {
+            return posting.delivery;
+        }
+
+        let client = http::client();
+        let url = self.config.instance.trim_end_matches('/').to_string() + "/api/v1/statuses";
+
+        let mut statuses = vec![summary(announcement) + "\n\n" + &self.config.link];
+        if self.config.thread {
+            statuses.extend(split(&long_form(announcement), self.config.max_length));
+        }
+
+        let mut progress = posting.progress(instance);
+        let mut result = Ok(());
+        for status in statuses.into_iter().skip(progress.sent) {
+            let response = client
+                .post(&url)
+                .bearer_auth(&self.config.access_token)
+                .json(&json!({
+                    "status": status,
+                    "visibility": self.config.visibility,
+                    "in_reply_to_id": progress.last,
+                }))
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+
+            match response {
+                Ok(r) => match r.json::<Status>().await {
+                    Ok(s) => {
+                        progress.sent += 1;
+                        progress.last = Some(s.id);
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Unable to read the Mastodon status");
+                        result = Err(e);
+                        break;
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!(error = %e, "Mastodon account cannot be posted to");
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+
+        let target = format!("Mastodon account on {}", instance);
+        posting.record_progress(instance, target, progress, &result);
+        posting.delivery
+    }
+}
+
+#[async_trait]
+impl Sink for MastodonSink {
+    fn name(&self) -> &'static str {
+        "mastodon"
+    }
+
+    async fn publish(&self, announcement: &Announcement) -> Delivery {
+        self.post(announcement, None).await
+    }
+
+    async fn redeliver(&self) -> Delivery {
+        let mut delivery = Delivery::default();
+        let instances = [self.config.instance.clone()];
+        for queued in self.outbox.take(self.name(), &instances) {
+            let account_delivery = self.post(&queued.announcement, Some(&queued));
+            delivery.merge(account_delivery.await);
+        }
+        delivery
+    }
+}
+
+// Summarize the announcement by counting the entries of each section, e.g. "Veloren weekly update:
+// 5 additions, 3 fixes" for Veloren.
+fn summary(announcement: &Announcement) -> String {
+    let mut counts: Vec<(&str, usize)> = vec![];
+    for line in &announcement.changes {
+        if let Some(s) = line.strip_prefix("## ") {
+            counts.push((s, 0));
+        } else if let Some((_, n)) = counts.last_mut() {
+            *n += 1;
+        }
+    }
+
+    let mut parts = counts
+        .into_iter()
+        .filter(|(_, n)| *n > 0)
+        .map(|(s, n)| format!("{} {}", n, noun(s, n)))
+        .collect::<Vec<_>>();
+
+    let devblogs = &announcement.devblogs;
+    if !devblogs.is_empty() {
+        parts.push(format!(
+            "{} {}",
+            devblogs.len(),
+            noun("Blog post(s)", devblogs.len())
+        ));
+    }
+
+    format!("{} weekly update: {}", announcement.name, parts.join(", "))
+}
+
+fn noun(section: &str, n: usize) -> String {
+    let (singular, plural) = match section {
+        "Added" => ("addition", "additions"),
+        "Changed" => ("change", "changes"),
+        "Removed" => ("removal", "removals"),
+        "Fixed" => ("fix", "fixes"),
+        "Blog post(s)" => ("new blog post", "new blog posts"),
+        s => return s.to_lowercase(),
+    };
+
+    match n {
+        1 => singular.to_string(),
+        _ => plural.to_string(),
+    }
+}
+
+// Mastodon has no markdown, so the headers are displayed as plain lines.
+fn long_form(announcement: &Announcement) -> Vec<String> {
+    let mut lines = vec![];
+
+    for line in &announcement.changes {
+        match line.strip_prefix("## ") {
+            Some(s) => lines.push(format!("\n{}:", s)),
+            None => lines.push(line.clone()),
+        }
+    }
+
+    for d in &announcement.devblogs {
+        lines.push(format!("- {} {}", d.title, d.url));
+    }
+
+    lines
+}
+
+// Join the lines into as few statuses as possible without splitting a line, unless the line itself
+// is too long.
+fn split(lines: &[String], max_length: usize) -> Vec<String> {
+    let mut statuses: Vec<String> = vec![];
+
+    for line in lines.iter().flat_map(|l| wrap(l, max_length)) {
+        match statuses.last_mut() {
+            Some(last) if last.chars().count() + line.chars().count() < max_length => {
+                last.push('\n');
+                last.push_str(&line);
+            }
+            _ => statuses.push(line.trim_start().to_string()),
+        }
+    }
+
+    statuses
+}
+
+// Wrap a line at word boundaries so that no piece is longer than `max_length` characters.
+fn wrap(line: &str, max_length: usize) -> Vec<String> {
+    let mut pieces: Vec<String> = vec![];
+
+    for word in line.split(' ') {
+        match pieces.last_mut() {
+            Some(last) if last.chars().count() + word.chars().count() < max_length => {
+                last.push(' ');
+                last.push_str(word);
+            }
+            _ => pieces.push(word.chars().take(max_length).collect()),
+        }
+    }
+
+    pieces
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::Url;
+use serde_json::json;
+
+use crate::announcement::Announcement;
+use crate::config::MatrixConfig;
+use crate::html;
+use crate::http;
+use crate::sinks::outbox::Posting;
+use crate::sinks::outbox::Queued;
+use crate::sinks::Delivery;
+use crate::sinks::Outbox;
+use crate::sinks::Sink;
+
+pub struct MatrixSink {
+    config: MatrixConfig,
+    outbox: Arc<Outbox>,
+}
+
+impl MatrixSink {
+    pub fn new(config: MatrixConfig, outbox: Arc<Outbox>) -> MatrixSink {
+        MatrixSink { config, outbox }
+    }
+
+    // Post the announcement to the rooms, queueing it for those that can't be posted to. The
+    // plain text body is the same markdown that is posted to Discord, while clients that support
+    // it display the html version.
+    async fn post(
+        &self,
+        announcement: &Announcement,
+        rooms: &[String],
+        queued: Option<&Queued>,
+    ) -> Delivery {
+        let client = http::client();
+
+        // Matrix has no embeds, so the devblogs are listed as links instead.
+        let body = announcement
+            .devblogs
+            .iter()
+            .fold(announcement.message(), |s, d| {
+                s + &format!("\n- [{}]({})", d.title, d.url)
+            });
+        let html = format!("<h1>{}</h1>", announcement.heading())
+            + &html::render(&announcement.changes, &announcement.devblogs);
+
+        // The homeserver ignores a message whose transaction ID it has already seen, so deriving it
+        // from the announcement means that retries and later runs never post it to a room twice.
+        let hash = announcement.hash();
+
+        let mut posting = Posting::new(&self.outbox, self.name(), announcement, queued);
+        let homeserver = match Url::parse(&self.config.homeserver) {
+            Ok(u) => u,
+            Err(e) => {
+                tracing::error!(homeserver = self.config.homeserver, error = %e, "Invalid Matrix homeserver");
+                for room in rooms {
+                    posting
+                        .delivery
+                        .failed
+                        .push(format!("Matrix room {}", room));
+                }
+                return posting.delivery;
+            }
+        };
+
+        for room in rooms {
+            if posting.done(room) {
+                continue;
+            }
+
+            let mut url = homeserver.clone();
+
+            url.path_segments_mut()
+                .unwrap()
+                .pop_if_empty()
+                .extend([
+                    "_matrix",
+                    "client",
+                    "v3",
+                    "rooms",
+                    room,
+                    "send",
+                    "m.room.message",
+                ])
+                .push(&format!("veloren-changelog-{}-{}", hash, room));
+
+            let result = client
+                .put(url)
+                .bearer_auth(&self.config.access_token)
+                .json(&json!({
+                    "msgtype": "m.notice",
+                    "body": body,
+                    "format": "org.matrix.custom.html",
+                    "formatted_body": html,
+                }))
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+
+            if let Err(e) = &result {
+                tracing::warn!(room, error = %e, "Matrix room cannot be written to");
+            }
+            posting.record(room, format!("Matrix room {}", room), &result);
+        }
+
+        posting.delivery
+    }
+}
+
+#[async_trait]
+impl Sink for MatrixSink {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    async fn publish(&self, announcement: &Announcement) -> Delivery {
+        self.post(announcement, &self.config.rooms, None).await
+    }
+
+    async fn redeliver(&self) -> Delivery {
+        let mut delivery = Delivery::default();
+        for queued in self.outbox.take(self.name(), &self.config.rooms) {
+            let rooms = [queued.target.clone()];
+            let room_delivery = self.post(&queued.announcement, &rooms, Some(&queued));
+            delivery.merge(room_delivery.await);
+        }
+        delivery
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::announcement::Announcement;
+use crate::config::Config;
+
+mod capture;
+pub mod discord;
+mod feed;
+mod irc;
+mod mastodon;
+mod matrix;
+mod outbox;
+mod site;
+mod slack;
+mod telegram;
+
+pub use capture::capture;
+
+// Whether announcements are captured instead of posted anywhere.
+pub fn capturing() -> bool {
+    capture::from_path().is_some()
+}
+pub use discord::Discord;
+use outbox::Outbox;
+
+// A platform that announcements are posted to. Sinks report their own errors, so that a failure on
+// one platform doesn't prevent posting to the others.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn publish(&self, announcement: &Announcement) -> Delivery;
+
+    // Post what couldn't be delivered on earlier runs, for sinks that queue their failed targets.
+    async fn redeliver(&self) -> Delivery {
+        Delivery::default()
+    }
+
+    // Correct the announcements that had the given entries, which have been removed upstream, for
+    // sinks that remember what they posted.
+    async fn correct(&self, _entries: &[String]) -> Delivery {
+        Delivery::default()
+    }
+}
+
+// The outcome of publishing to the targets (channels, rooms, chats, ...) of a sink.
+#[derive(Default)]
+pub struct Delivery {
+    pub sent: usize,
+    // A description of each target that couldn't be posted to.
+    pub failed: Vec<String>,
+    // A description of each target that couldn't be posted to, where the sink has queued the
+    // announcement to post it on the next run instead.
+    pub queued: Vec<String>,
+}
+
+impl Delivery {
+    pub fn record<T, E>(&mut self, target: impl Into<String>, result: &Result<T, E>) {
+        match result {
+            Ok(_) => self.sent += 1,
+            Err(_) => self.failed.push(target.into()),
+        }
+    }
+
+    pub fn merge(&mut self, other: Delivery) {
+        self.sent += other.sent;
+        self.failed.extend(other.failed);
+        self.queued.extend(other.queued);
+    }
+}
+
+// The title of the feed and the site, and of the announcements on them.
+fn news_title(name: &str) -> String {
+    format!("{} News", name)
+}
+
+// Build the sinks enabled by the config, which keep their files in the state directory. The feed
+// and Discord are always enabled. Discord is last since it may have to connect first. While
+// capturing, nothing is posted anywhere.
+pub fn from_config(config: &Config, discord: Discord, state_dir: &Path) -> Vec<Box<dyn Sink>> {
+    if let Some(sink) = capture::from_path() {
+        return vec![Box::new(sink)];
+    }
+
+    // The feed and the site are named after the first project, like the slash commands.
+    let project = config.project(None).unwrap_or_default();
+    let outbox = Arc::new(Outbox::load(state_dir));
+    let feed = feed::FeedSink::new(state_dir, &project, outbox.clone());
+    let mut sinks: Vec<Box<dyn Sink>> = vec![Box::new(feed)];
+
+    if let Some(c) = &config.matrix {
+        sinks.push(Box::new(matrix::MatrixSink::new(c.clone(), outbox.clone())));
+    }
+
+    if let Some(c) = &config.telegram {
+        sinks.push(Box::new(telegram::TelegramSink::new(
+            c.clone(),
+            outbox.clone(),
+        )));
+    }
+
+    if let Some(c) = &config.mastodon {
+        sinks.push(Box::new(mastodon::MastodonSink::new(
+            c.clone(),
+            outbox.clone(),
+        )));
+    }
+
+    if let Some(c) = &config.slack {
+        sinks.push(Box::new(slack::SlackSink::new(c.clone(), outbox.clone())));
+    }
+
+    if let Some(c) = &config.irc {
+        sinks.push(Box::new(irc::IrcSink::new(c.clone(), outbox.clone())));
+    }
+
+    if let Some(c) = &config.site {
+        sinks.push(Box::new(site::SiteSink::new(
+            c.clone(),
+            &project,
+            outbox.clone(),
+        )));
+    }
+
+    sinks.push(Box::new(discord::DiscordSink::new(
+        config.discord.clone(),
+        config.retry.clone(),
+        discord,
+    )));
+    sinks
+}
@@ -0,0 +1,323 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::announcement::Announcement;
+use crate::sinks::Delivery;
+use crate::state;
+
+const OUTBOX_PATH: &str = "outbox.json";
+
+// Seconds after which an announcement that couldn't be delivered to a target is dropped, like those
+// of the guilds.
+const MAX_QUEUED_AGE: i64 = 7 * 24 * 3600;
+
+// How long posted announcements stay in the ledger, which covers every retry of an announcement
+// since those are dropped after the same time.
+const MAX_LEDGER_AGE: i64 = MAX_QUEUED_AGE;
+
+// What the sinks other than Discord posted recently, and what they couldn't post, which Discord
+// keeps with the settings of each guild instead. The outbox is saved as soon as it is changed, so
+// that it is kept even if the state isn't.
+pub struct Outbox {
+    path: PathBuf,
+    contents: Mutex<Contents>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Contents {
+    // The announcements posted recently, oldest first, so that an announcement that is posted
+    // again by a retry or an overlapping run is recognized.
+    ledger: Vec<Sent>,
+    // Announcements that couldn't be posted, oldest first, which are retried on the next run.
+    queued: Vec<Queued>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Sent {
+    sink: String,
+    target: String,
+    // The hash of the announcement.
+    hash: String,
+    // Number of messages that the announcement was posted as.
+    #[serde(default)]
+    messages: usize,
+    // Unix timestamp.
+    posted_at: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Queued {
+    sink: String,
+    // The target as the sink is configured with it, e.g. the ID of a room.
+    pub target: String,
+    pub announcement: Announcement,
+    // Unix timestamp of when the announcement was first queued.
+    pub queued_at: i64,
+    // What was posted before it failed, which isn't posted again.
+    #[serde(default)]
+    pub progress: Progress,
+}
+
+// How far posting an announcement that is split into several messages got.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Progress {
+    // Number of messages posted.
+    pub sent: usize,
+    // The ID of the last message posted, on platforms where the next one replies to it.
+    pub last: Option<String>,
+}
+
+impl Outbox {
+    pub fn load(dir: &Path) -> Outbox {
+        let path = dir.join(OUTBOX_PATH);
+        let contents = state::load_sealed(&path);
+        Outbox {
+            path,
+            contents: Mutex::new(contents),
+        }
+    }
+
+    // Take the announcements queued for the sink, except for the outdated ones and those for
+    // targets that are no longer configured. Anything that fails again is queued again by posting.
+    pub fn take(&self, sink: &str, targets: &[String]) -> Vec<Queued> {
+        let now = chrono::Utc::now().timestamp();
+        let mut taken = vec![];
+        self.update(|c| {
+            let (mine, others) = std::mem::take(&mut c.queued)
+                .into_iter()
+                .partition(|q| q.sink == sink);
+            c.queued = others;
+            taken = mine;
+        });
+
+        taken.retain(|q| {
+            if now - q.queued_at > MAX_QUEUED_AGE {
+                tracing::warn!(sink, "Dropping an outdated undelivered announcement");
+                return false;
+            }
+            if !targets.contains(&q.target) {
+                tracing::warn!(
+                    sink,
+                    "Target is no longer configured, dropping the announcement"
+                );
+                return false;
+            }
+            true
+        });
+        taken
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Contents)) {
+        let mut contents = self.contents.lock().unwrap();
+        f(&mut contents);
+
+        state::write_sealed(
+            &self.path,
+            &serde_json::to_string_pretty(&*contents).unwrap(),
+        );
+    }
+}
+
+// Posting an announcement to the targets of a sink, where every target that it was already posted
+// to is skipped, and it is queued for every target that it can't be posted to.
+pub struct Posting<'a> {
+    outbox: &'a Outbox,
+    sink: &'static str,
+    announcement: &'a Announcement,
+    hash: String,
+    queued: Option<&'a Queued>,
+    queued_at: i64,
+    pub delivery: Delivery,
+}
+
+impl<'a> Posting<'a> {
+    // Announcements that are posted again keep the time they were first queued, and are resumed
+    // where they failed.
+    pub fn new(
+        outbox: &'a Outbox,
+        sink: &'static str,
+        announcement: &'a Announcement,
+        queued: Option<&'a Queued>,
+    ) -> Posting<'a> {
+        Posting {
+            outbox,
+            sink,
+            announcement,
+            hash: announcement.hash(),
+            queued,
+            queued_at: queued.map_or_else(|| chrono::Utc::now().timestamp(), |q| q.queued_at),
+            delivery: Delivery::default(),
+        }
+    }
+
+    // Unix timestamp of when the announcement was first posted, which it keeps when it is posted
+    // again.
+    pub fn posted_at(&self) -> i64 {
+        self.queued_at
+    }
+
+    // What was already posted to the target, when posting it again.
+    pub fn progress(&self, target: &str) -> Progress {
+        self.queued
+            .filter(|q| q.target == target)
+            .map(|q| q.progress.clone())
+            .unwrap_or_default()
+    }
+
+    // Whether the announcement was already posted to the target, which counts as sent.
+    pub fn done(&mut self, target: &str) -> bool {
+        let contents = self.outbox.contents.lock().unwrap();
+        let posted = contents
+            .ledger
+            .iter()
+            .any(|s| s.sink == self.sink && s.target == target && s.hash == self.hash);
+        if posted {
+            tracing::info!(
+                sink = self.sink,
+                "Announcement was already posted to the target"
+            );
+            self.delivery.sent += 1;
+        }
+        posted
+    }
+
+    // Record whether the announcement was posted to the target as a single message, which
+    // `description` describes in the logs.
+    pub fn record<T, E>(&mut self, target: &str, description: String, result: &Result<T, E>) {
+        let progress = Progress {
+            sent: usize::from(result.is_ok()),
+            last: None,
+        };
+        self.record_progress(target, description, progress, result);
+    }
+
+    // Record whether every message of the announcement was posted to the target, and how far it
+    // got otherwise.
+    pub fn record_progress<T, E>(
+        &mut self,
+        target: &str,
+        description: String,
+        progress: Progress,
+        result: &Result<T, E>,
+    ) {
+        let now = chrono::Utc::now().timestamp();
+        match result {
+            Ok(_) => {
+                self.outbox.update(|c| {
+                    c.ledger.retain(|s| now - s.posted_at <= MAX_LEDGER_AGE);
+                    c.ledger.push(Sent {
+                        sink: self.sink.to_string(),
+                        target: target.to_string(),
+                        hash: self.hash.clone(),
+                        messages: progress.sent,
+                        posted_at: now,
+                    });
+                });
+                self.delivery.sent += 1;
+            }
+            Err(_) => {
+                self.outbox.update(|c| {
+                    c.queued.push(Queued {
+                        sink: self.sink.to_string(),
+                        target: target.to_string(),
+                        announcement: self.announcement.clone(),
+                        queued_at: self.queued_at,
+                        progress,
+                    })
+                });
+                self.delivery.queued.push(description);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AnnouncementConfig;
+    use crate::config::ProjectConfig;
+    use crate::sources::Update;
+    use crate::state::test_dir;
+
+    fn announcement() -> Announcement {
+        let updates = vec![Update::Section {
+            title: "Added".to_string(),
+            entries: vec!["- A thing".to_string()],
+        }];
+        let project = ProjectConfig::default();
+        Announcement::new(updates, &AnnouncementConfig::default(), &project).unwrap()
+    }
+
+    #[test]
+    fn failed_targets_are_queued() {
+        let dir = test_dir("outbox-queued");
+        let outbox = Outbox::load(&dir);
+        let announcement = announcement();
+
+        let mut posting = Posting::new(&outbox, "matrix", &announcement, None);
+        posting.record("a", "Room a".to_string(), &Ok::<_, ()>(()));
+        posting.record("b", "Room b".to_string(), &Err::<(), _>(()));
+        assert_eq!(posting.delivery.sent, 1);
+        assert_eq!(posting.delivery.queued, ["Room b"]);
+
+        let outbox = Outbox::load(&dir);
+        let targets = ["a".to_string(), "b".to_string()];
+        let queued = outbox.take("matrix", &targets);
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].target, "b");
+        assert!(outbox.take("matrix", &targets).is_empty());
+    }
+
+    #[test]
+    fn posted_targets_are_skipped() {
+        let dir = test_dir("outbox-posted");
+        let outbox = Outbox::load(&dir);
+        let announcement = announcement();
+
+        let mut posting = Posting::new(&outbox, "matrix", &announcement, None);
+        posting.record("a", "Room a".to_string(), &Ok::<_, ()>(()));
+
+        let outbox = Outbox::load(&dir);
+        let mut posting = Posting::new(&outbox, "matrix", &announcement, None);
+        assert!(posting.done("a"));
+        assert!(!posting.done("b"));
+        let mut posting = Posting::new(&outbox, "slack", &announcement, None);
+        assert!(!posting.done("a"));
+    }
+
+    #[test]
+    fn queued_announcements_resume_where_they_failed() {
+        let dir = test_dir("outbox-progress");
+        let outbox = Outbox::load(&dir);
+        let announcement = announcement();
+
+        let mut posting = Posting::new(&outbox, "telegram", &announcement, None);
+        let progress = Progress {
+            sent: 2,
+            last: Some("2".to_string()),
+        };
+        posting.record_progress("a", "Chat a".to_string(), progress, &Err::<(), _>(()));
+
+        let queued = outbox.take("telegram", &["a".to_string()]);
+        let posting = Posting::new(&outbox, "telegram", &announcement, Some(&queued[0]));
+        assert_eq!(posting.progress("a").sent, 2);
+        assert_eq!(posting.progress("a").last.as_deref(), Some("2"));
+        assert_eq!(posting.progress("b").sent, 0);
+    }
+
+    #[test]
+    fn unconfigured_targets_are_dropped() {
+        let dir = test_dir("outbox-unconfigured");
+        let outbox = Outbox::load(&dir);
+        let announcement = announcement();
+
+        let mut posting = Posting::new(&outbox, "matrix", &announcement, None);
+        posting.record("a", "Room a".to_string(), &Err::<(), _>(()));
+        assert!(outbox.take("matrix", &["b".to_string()]).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::announcement::Announcement;
+use crate::config::ProjectConfig;
+use crate::config::SiteConfig;
+use crate::html;
+use crate::sinks::news_title;
+use crate::sinks::outbox::Posting;
+use crate::sinks::outbox::Queued;
+use crate::sinks::Delivery;
+use crate::sinks::Outbox;
+use crate::sinks::Sink;
+use crate::state;
+
+const INDEX_NAME: &str = "index.html";
+
+// Writes every announcement to its own page of a static site, with an index of all pages.
+pub struct SiteSink {
+    config: SiteConfig,
+    // The title of the index.
+    title: String,
+    outbox: Arc<Outbox>,
+}
+
+impl SiteSink {
+    pub fn new(config: SiteConfig, project: &ProjectConfig, outbox: Arc<Outbox>) -> SiteSink {
+        SiteSink {
+            config,
+            title: news_title(project.name()),
+            outbox,
+        }
+    }
+
+    fn post(&self, announcement: &Announcement, queued: Option<&Queued>) -> Delivery {
+        let dir = &self.config.dir;
+        let mut posting = Posting::new(&self.outbox, self.name(), announcement, queued);
+        if posting.done(dir) {
+            return posting.delivery;
+        }
+
+        let result = write(Path::new(dir), &self.title, announcement);
+        if let Err(e) = &result {
+            tracing::warn!(dir, error = %e, "Unable to write the site");
+        }
+        posting.record(dir, format!("Site in {}", dir), &result);
+        posting.delivery
+    }
+}
+
+#[async_trait]
+impl Sink for SiteSink {
+    fn name(&self) -> &'static str {
+        "site"
+    }
+
+    async fn publish(&self, announcement: &Announcement) -> Delivery {
+        self.post(announcement, None)
+    }
+
+    async fn redeliver(&self) -> Delivery {
+        let mut delivery = Delivery::default();
+        let dirs = [self.config.dir.clone()];
+        for queued in self.outbox.take(self.name(), &dirs) {
+            delivery.merge(self.post(&queued.announcement, Some(&queued)));
+        }
+        delivery
+    }
+}
+
+// Add a page for the announcement, and rewrite the index to include it. The pages are named by the
+// time they were written, so sorting them by name sorts them by time, followed by the hash of the
+// announcement, so that announcements written in the same second don't replace each other.
+fn write(dir: &Path, index_title: &str, announcement: &Announcement) -> io::Result<()> {
+    let now = chrono::Utc::now();
+    fs::create_dir_all(dir)?;
+
+    let title = format!(
+        "{} ({})",
+        news_title(&announcement.name),
+        now.format("%Y-%m-%d")
+    );
+    let body = format!(
+        "{}<p><a href=\"{}\">All announcements</a></p>",
+        html::render(&announcement.changes, &announcement.devblogs),
+        INDEX_NAME
+    );
+    let name = format!(
+        "{}-{}.html",
+        now.format("%Y-%m-%d-%H%M%S"),
+        announcement.hash()
+    );
+    state::try_write_atomic(&dir.join(name), page(&title, &body))?;
+
+    let mut names = fs::read_dir(dir)?
+        .filter_map(|e| e.ok()?.file_name().into_string().ok())
+        .filter(|n| n.ends_with(".html") && n != INDEX_NAME)
+        .collect::<Vec<_>>();
+    names.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut index = String::from("<ul>");
+    for name in names {
+        index.push_str(&format!(
+            "<li><a href=\"{}\">{} ({})</a></li>",
+            html::escape(&name),
+            html::escape(index_title),
+            html::escape(name.get(..10).unwrap_or(&name))
+        ));
+    }
+    index.push_str("</ul>");
+    state::try_write_atomic(&dir.join(INDEX_NAME), page(index_title, &index))
+}
+
+// A standalone page, with the title repeated as its heading.
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n{1}\n</body>\n</html>\n",
+        html::escape(title),
+        body
+    )
+}
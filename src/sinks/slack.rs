@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::announcement::Announcement;
+use crate::config::SlackConfig;
+use crate::http;
+use crate::sinks::outbox::Posting;
+use crate::sinks::outbox::Queued;
+use crate::sinks::Delivery;
+use crate::sinks::Outbox;
+use crate::sinks::Sink;
+
+// Limits of Block Kit: blocks per message, and characters per text of a section block.
+const MAX_BLOCKS: usize = 50;
+const MAX_TEXT_LENGTH: usize = 3000;
+
+pub struct SlackSink {
+    config: SlackConfig,
+    outbox: Arc<Outbox>,
+}
+
+impl SlackSink {
+    pub fn new(config: SlackConfig, outbox: Arc<Outbox>) -> SlackSink {
+        SlackSink { config, outbox }
+    }
+
+    // Post the announcement to the incoming webhooks, queueing it for those that can't be posted
+    // to. A queued announcement is resumed with the first message that wasn't posted.
+    async fn post(
+        &self,
+        announcement: &Announcement,
+        webhooks: &[String],
+        queued: Option<&Queued>,
+    ) -> Delivery {
+        let client = http::client();
+        let message = announcement.message();
+        let blocks = render(announcement);
+
+        let mut posting = Posting::new(&self.outbox, self.name(), announcement, queued);
+        for webhook in webhooks {
+            if posting.done(webhook) {
+                continue;
+            }
+
+            let mut progress = posting.progress(webhook);
+            let mut result = Ok(());
+            for chunk in blocks.chunks(MAX_BLOCKS).skip(progress.sent) {
+                let response = client
+                    .post(webhook)
+                    .json(&json!({
+                        "text": message,
+                        "blocks": chunk,
+                    }))
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status());
+
+                if let Err(e) = response {
+                    tracing::warn!(error = %e, "Slack webhook cannot be posted to");
+                    result = Err(e);
+                    break;
+                }
+                progress.sent += 1;
+            }
+
+            // Webhook URLs are secret, so they aren't used to describe the target.
+            let target = "Slack webhook".to_string();
+            posting.record_progress(webhook, target, progress, &result);
+        }
+
+        posting.delivery
+    }
+}
+
+#[async_trait]
+impl Sink for SlackSink {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    async fn publish(&self, announcement: &Announcement) -> Delivery {
+        self.post(announcement, &self.config.webhooks, None).await
+    }
+
+    async fn redeliver(&self) -> Delivery {
+        let mut delivery = Delivery::default();
+        for queued in self.outbox.take(self.name(), &self.config.webhooks) {
+            let webhooks = [queued.target.clone()];
+            let webhook_delivery = self.post(&queued.announcement, &webhooks, Some(&queued));
+            delivery.merge(webhook_delivery.await);
+        }
+        delivery
+    }
+}
+
+// Render every section as a header block followed by section blocks holding its bullets.
+fn render(announcement: &Announcement) -> Vec<Value> {
+    let mut blocks = vec![header(announcement.heading())];
+    let mut bullets: Vec<String> = vec![];
+
+    for line in &announcement.changes {
+        match line.strip_prefix("- ") {
+            Some(s) => bullets.push(format!("• {}", escape(s))),
+            None => {
+                blocks.extend(sections(&bullets));
+                bullets.clear();
+                blocks.push(header(line.trim_start_matches('#').trim()));
+            }
+        }
+    }
+
+    bullets.extend(
+        announcement
+            .devblogs
+            .iter()
+            .map(|d| format!("• <{}|{}>", d.url, escape(&d.title))),
+    );
+    blocks.extend(sections(&bullets));
+
+    blocks
+}
+
+fn header(text: &str) -> Value {
+    json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": text },
+    })
+}
+
+// Group the bullets into as few section blocks as the text length limit allows.
+fn sections(bullets: &[String]) -> Vec<Value> {
+    let mut texts: Vec<String> = vec![];
+
+    for bullet in bullets {
+        let bullet = bullet.chars().take(MAX_TEXT_LENGTH).collect::<String>();
+        match texts.last_mut() {
+            Some(last) if last.chars().count() + bullet.chars().count() < MAX_TEXT_LENGTH => {
+                last.push('\n');
+                last.push_str(&bullet);
+            }
+            _ => texts.push(bullet),
+        }
+    }
+
+    texts
+        .into_iter()
+        .map(|text| {
+            json!({
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": text },
+            })
+        })
+        .collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
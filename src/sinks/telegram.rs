@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::announcement::Announcement;
+use crate::config::TelegramConfig;
+use crate::http;
+use crate::sinks::outbox::Posting;
+use crate::sinks::outbox::Queued;
+use crate::sinks::Delivery;
+use crate::sinks::Outbox;
+use crate::sinks::Sink;
+
+const API_URL: &str = "https://api.telegram.org";
+
+// Maximum length of a single Telegram message.
+const MAX_LENGTH: usize = 4096;
+
+pub struct TelegramSink {
+    config: TelegramConfig,
+    outbox: Arc<Outbox>,
+}
+
+impl TelegramSink {
+    pub fn new(config: TelegramConfig, outbox: Arc<Outbox>) -> TelegramSink {
+        TelegramSink { config, outbox }
+    }
+
+    // Post the announcement to the chats, queueing it for those that can't be posted to. A queued
+    // announcement is resumed with the first message that wasn't posted.
+    async fn post(
+        &self,
+        announcement: &Announcement,
+        chats: &[String],
+        queued: Option<&Queued>,
+    ) -> Delivery {
+        let client = http::client();
+        let url = format!("{}/bot{}/sendMessage", API_URL, self.config.bot_token);
+        let messages = split(&render(announcement));
+
+        let mut posting = Posting::new(&self.outbox, self.name(), announcement, queued);
+        for chat in chats {
+            if posting.done(chat) {
+                continue;
+            }
+
+            let mut progress = posting.progress(chat);
+            let mut result = Ok(());
+            for text in messages.iter().skip(progress.sent) {
+                let response = client
+                    .post(&url)
+                    .json(&json!({
+                        "chat_id": chat,
+                        "text": text,
+                        "parse_mode": "MarkdownV2",
+                        "disable_web_page_preview": true,
+                    }))
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status());
+
+                if let Err(e) = response {
+                    tracing::warn!(chat, error = %e, "Telegram chat cannot be written to");
+                    result = Err(e);
+                    break;
+                }
+                progress.sent += 1;
+            }
+
+            let target = format!("Telegram chat {}", chat);
+            posting.record_progress(chat, target, progress, &result);
+        }
+
+        posting.delivery
+    }
+}
+
+#[async_trait]
+impl Sink for TelegramSink {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    async fn publish(&self, announcement: &Announcement) -> Delivery {
+        self.post(announcement, &self.config.chats, None).await
+    }
+
+    async fn redeliver(&self) -> Delivery {
+        let mut delivery = Delivery::default();
+        for queued in self.outbox.take(self.name(), &self.config.chats) {
+            let chats = [queued.target.clone()];
+            let chat_delivery = self.post(&queued.announcement, &chats, Some(&queued));
+            delivery.merge(chat_delivery.await);
+        }
+        delivery
+    }
+}
+
+// Render the announcement as MarkdownV2. Section headers are made bold, and everything taken from
+// the changelog is escaped so that it is displayed literally.
+fn render(announcement: &Announcement) -> Vec<String> {
+    let mut lines = vec![format!("*{}*", escape(announcement.heading()))];
+
+    for line in &announcement.changes {
+        match line.strip_prefix("- ") {
+            Some(s) => lines.push(format!("• {}", escape(s))),
+            None => lines.push(format!(
+                "\n*{}*",
+                escape(line.trim_start_matches('#').trim())
+            )),
+        }
+    }
+
+    for d in &announcement.devblogs {
+        lines.push(format!("• [{}]({})", escape(&d.title), escape_url(&d.url)));
+    }
+
+    lines
+}
+
+// Join the lines into as few messages as possible without splitting a line, unless the line itself
+// is too long.
+fn split(lines: &[String]) -> Vec<String> {
+    let mut messages = vec![String::new()];
+
+    for line in lines.iter().flat_map(|l| pieces(l)) {
+        let last = messages.last_mut().unwrap();
+        if !last.is_empty() && last.chars().count() + line.chars().count() + 1 > MAX_LENGTH {
+            messages.push(String::new());
+        }
+
+        let last = messages.last_mut().unwrap();
+        if !last.is_empty() {
+            last.push('\n');
+        }
+        last.push_str(&line);
+    }
+
+    messages
+}
+
+// The line cut into pieces that fit into a message, keeping every escaped character together with
+// its backslash.
+fn pieces(line: &str) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut length = 0;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        let escaped = match c {
+            '\\' => chars.next(),
+            _ => None,
+        };
+        let width = 1 + escaped.is_some() as usize;
+        if length + width > MAX_LENGTH {
+            pieces.push(String::new());
+            length = 0;
+        }
+
+        let last = pieces.last_mut().unwrap();
+        last.push(c);
+        last.extend(escaped);
+        length += width;
+    }
+
+    pieces
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "_*[]()~`>#+-=|{}.!\\".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Inside the url part of a link, only `)` and `\` have to be escaped.
+fn escape_url(s: &str) -> String {
+    s.replace('\\', "\\\\").replace(')', "\\)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_joins_short_lines() {
+        let lines = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(split(&lines), vec!["a\nb"]);
+    }
+
+    #[test]
+    fn split_cuts_long_lines() {
+        let line = "x".repeat(MAX_LENGTH * 2 + 1);
+        let messages = split(&[line]);
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().all(|m| m.chars().count() <= MAX_LENGTH));
+    }
+
+    #[test]
+    fn split_keeps_escapes_together() {
+        // The escape of the last dot would start at the limit.
+        let line = "x".repeat(MAX_LENGTH - 1) + &escape(".");
+        let messages = split(&[line]);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1], "\\.");
+        assert!(messages.iter().all(|m| m.chars().count() <= MAX_LENGTH));
+    }
+}
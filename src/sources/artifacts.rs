@@ -0,0 +1,110 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::RetryConfig;
+use crate::http;
+use crate::sources::unseen;
+use crate::sources::Source;
+use crate::sources::Update;
+use crate::state::State;
+
+const ARTIFACTS_PATH: &str = "ARTIFACTS.md";
+
+// The downloads of a release, once they have been published on GitLab.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Artifacts {
+    pub version: String,
+    // The page of the release on GitLab.
+    pub url: String,
+    pub downloads: Vec<Download>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Download {
+    // What the download is for, usually the platform.
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(rename = "_links")]
+    links: ReleaseLinks,
+    assets: Assets,
+}
+
+#[derive(Deserialize)]
+struct ReleaseLinks {
+    #[serde(rename = "self")]
+    page: String,
+}
+
+#[derive(Deserialize)]
+struct Assets {
+    links: Vec<AssetLink>,
+}
+
+#[derive(Deserialize)]
+struct AssetLink {
+    name: String,
+    url: String,
+    direct_asset_url: Option<String>,
+}
+
+pub struct ArtifactsSource {
+    // The GitLab API of the project.
+    api_url: String,
+    retry: RetryConfig,
+}
+
+impl ArtifactsSource {
+    pub fn new(api_url: String, retry: RetryConfig) -> ArtifactsSource {
+        ArtifactsSource { api_url, retry }
+    }
+}
+
+#[async_trait]
+impl Source for ArtifactsSource {
+    fn name(&self) -> &'static str {
+        "artifacts"
+    }
+
+    // Releases are only announced once they have downloads, which are uploaded after the release is
+    // tagged.
+    async fn poll(&self, state: &State) -> reqwest::Result<Vec<Update>> {
+        let url = format!("{}/releases?per_page=20", self.api_url);
+        let releases: Vec<Release> = http::get_json(&self.retry, &url).await?;
+        let published = releases
+            .into_iter()
+            .filter(|r| !r.assets.links.is_empty())
+            .collect::<Vec<_>>();
+
+        let tags = published
+            .iter()
+            .map(|r| r.tag_name.clone())
+            .collect::<Vec<_>>();
+        let new = unseen(state, ARTIFACTS_PATH, &tags);
+
+        Ok(published
+            .into_iter()
+            .filter(|r| new.contains(&r.tag_name))
+            .map(|r| {
+                Update::Artifacts(Artifacts {
+                    version: r.tag_name.trim_start_matches('v').to_string(),
+                    url: r.links.page,
+                    downloads: r
+                        .assets
+                        .links
+                        .into_iter()
+                        .map(|l| Download {
+                            name: l.name,
+                            url: l.direct_asset_url.unwrap_or(l.url),
+                        })
+                        .collect(),
+                })
+            })
+            .collect())
+    }
+}
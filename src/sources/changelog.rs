@@ -0,0 +1,415 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use regex::Regex;
+use semver::Version;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::DownloadsConfig;
+use crate::config::RetryConfig;
+use crate::diff;
+use crate::http;
+use crate::sources::artifacts::Download;
+use crate::sources::Source;
+use crate::sources::Update;
+use crate::state::State;
+
+const CHANGELOG_PATH: &str = "CHANGELOG.md";
+const NEW_CONTRIBUTORS: &str = "New contributors";
+pub const CHANGELOG_URL: &str = "https://gitlab.com/veloren/veloren/-/raw/weekly/CHANGELOG.md";
+// The page of the same changelog, which is linked below the announcements.
+pub const CHANGELOG_PAGE_URL: &str =
+    "https://gitlab.com/veloren/veloren/-/blob/weekly/CHANGELOG.md";
+const BRANCH_URL: &str =
+    "https://gitlab.com/api/v4/projects/veloren%2Fveloren/repository/branches/weekly";
+
+#[derive(Deserialize)]
+struct Branch {
+    commit: Commit,
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    id: String,
+}
+
+// A version that was tagged since the changelog was last polled.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Release {
+    pub version: String,
+    // The section of the changelog for the version, as markdown lines.
+    pub notes: Vec<String>,
+    #[serde(default)]
+    pub launcher: Option<String>,
+    // The downloads of the version that could be found.
+    #[serde(default)]
+    pub downloads: Vec<Download>,
+    // The comparison with the previous version on GitLab, from the links at the end of the
+    // changelog.
+    #[serde(default)]
+    pub compare: Option<String>,
+    // The date in the section header, and the number of days since the previous version was
+    // released.
+    #[serde(default)]
+    pub date: Option<NaiveDate>,
+    #[serde(default)]
+    pub days: Option<i64>,
+}
+
+pub struct ChangelogSource {
+    url: String,
+    downloads: DownloadsConfig,
+    retry: RetryConfig,
+}
+
+impl ChangelogSource {
+    pub fn new(url: String, downloads: DownloadsConfig, retry: RetryConfig) -> ChangelogSource {
+        ChangelogSource {
+            url,
+            downloads,
+            retry,
+        }
+    }
+}
+
+#[async_trait]
+impl Source for ChangelogSource {
+    fn name(&self) -> &'static str {
+        "changelog"
+    }
+
+    async fn poll(&self, state: &State) -> reqwest::Result<Vec<Update>> {
+        // A saved changelog without the "Unreleased" section was cut off, and diffing against it
+        // would announce everything again, so it is replaced like a missing one.
+        let old = match state.read(CHANGELOG_PATH) {
+            Ok(s) if complete(&s) => s,
+            result => {
+                if result.is_ok() {
+                    tracing::error!("Saved changelog is incomplete, starting over");
+                    state.forget(CHANGELOG_PATH);
+                }
+                // Diffing against nothing would announce the whole history, so without a
+                // complete download to start from, there is nothing to announce yet.
+                match download(&self.retry, state, &self.url).await? {
+                    Some(s) => s,
+                    None => return Ok(vec![]),
+                }
+            }
+        };
+
+        // If the changelog hasn't changed, there is nothing to diff.
+        let Some(new) = download(&self.retry, state, &self.url).await? else {
+            return Ok(vec![]);
+        };
+
+        let mut updates = vec![];
+        let removed = diff::removed(&old, &new);
+        if !removed.is_empty() {
+            tracing::info!(
+                entries = removed.len(),
+                "Announced entries were removed upstream"
+            );
+            updates.push(Update::Retracted(removed));
+        }
+
+        for mut release in releases(&old, &new) {
+            release.launcher = self.downloads.launcher.clone();
+            release.downloads = downloads(&self.downloads, &self.retry, &release.version).await;
+            updates.push(Update::Release(release));
+        }
+
+        // Group the changed lines by the sub-section they belong to.
+        let changes = diff::diff(&old, &new);
+        let newcomers = contributors(&old, &changes);
+        for line in changes {
+            match line.strip_prefix("## ") {
+                Some(s) => updates.push(Update::Section {
+                    title: s.to_string(),
+                    entries: vec![],
+                }),
+                None => {
+                    if let Some(Update::Section { entries, .. }) = updates.last_mut() {
+                        entries.push(line);
+                    }
+                }
+            }
+        }
+
+        if !newcomers.is_empty() {
+            tracing::info!(contributors = newcomers.len(), "Found new contributors");
+            updates.push(Update::Section {
+                title: NEW_CONTRIBUTORS.to_string(),
+                entries: newcomers
+                    .iter()
+                    .map(|c| format!("- @{} made their first contribution", c))
+                    .collect(),
+            });
+        }
+
+        Ok(updates)
+    }
+}
+
+// The contributors credited in the new entries, as `@name` like GitLab and GitHub mention users,
+// whose name isn't anywhere in the old changelog yet, in the order they were first credited.
+fn contributors(old: &str, entries: &[String]) -> Vec<String> {
+    // The name must not follow a word or a slash, which would make it part of an email or a URL.
+    let mention =
+        Regex::new(r"(?:^|[^\w@/])@([A-Za-z0-9_](?:[A-Za-z0-9_.-]*[A-Za-z0-9_])?)").unwrap();
+    let mut known = mention
+        .captures_iter(old)
+        .map(|c| c[1].to_lowercase())
+        .collect::<HashSet<_>>();
+
+    entries
+        .iter()
+        .filter(|e| e.starts_with("- "))
+        .flat_map(|e| mention.captures_iter(e).map(|c| c[1].to_string()))
+        .filter(|name| known.insert(name.to_lowercase()))
+        .collect()
+}
+
+// Every versioned section of the changelog, newest first.
+pub fn all_releases(changelog: &str) -> Vec<Release> {
+    releases("", changelog)
+}
+
+// The lines of the "Unreleased" section of the changelog.
+pub fn unreleased(changelog: &str) -> Vec<String> {
+    changelog
+        .lines()
+        .skip_while(|l| !diff::is_unreleased(l))
+        .skip(1)
+        .take_while(|l| !l.starts_with("## "))
+        .map(|l| l.to_string())
+        .collect()
+}
+
+// Parse a version like `0.15.0`, with or without a leading `v`.
+pub fn parse_version(version: &str) -> Option<Version> {
+    Version::parse(version.trim().trim_start_matches('v')).ok()
+}
+
+// Group the notes of a release by their sub-sections, like the entries found by polling.
+pub fn sections(notes: &[String]) -> Vec<Update> {
+    let mut updates = vec![];
+    for line in notes {
+        if let Some(s) = line.strip_prefix("### ") {
+            updates.push(Update::Section {
+                title: s.to_string(),
+                entries: vec![],
+            });
+        } else if let Some(Update::Section { entries, .. }) = updates.last_mut() {
+            // Lines that don't start with a bullet point belong to the previous entry.
+            if line.starts_with("- ") {
+                entries.push(line.clone());
+            } else if let Some(entry) = entries.last_mut().filter(|_| !line.trim().is_empty()) {
+                entry.push_str(&diff::continuation(line));
+            }
+        }
+    }
+    updates
+}
+
+// Find the versioned sections of the new changelog that aren't in the old one, newest first.
+// Versions are tagged by renaming the "Unreleased" section, so their entries have usually been
+// announced already.
+fn releases(old: &str, new: &str) -> Vec<Release> {
+    let old_versions = old.lines().filter_map(version).collect::<HashSet<_>>();
+    let links = links(new);
+
+    let mut releases: Vec<Release> = vec![];
+    let mut current: Option<Release> = None;
+    for line in new.lines() {
+        if line.starts_with("## ") {
+            releases.extend(current.take());
+            current = version(line).map(|v| Release {
+                version: v.to_string(),
+                notes: vec![],
+                launcher: None,
+                downloads: vec![],
+                compare: links.get(v).cloned(),
+                date: date(line),
+                days: None,
+            });
+        } else if reference(line).is_some() {
+            // The links at the end aren't part of the oldest version.
+            continue;
+        } else if let Some(release) = &mut current {
+            release.notes.push(line.to_string());
+        }
+    }
+    releases.extend(current);
+
+    // Sections are usually ordered already, but versions that can't be parsed go last. The
+    // previous version is only known once they are sorted.
+    releases.sort_by_key(|r| Reverse(parse_version(&r.version)));
+    for i in 0..releases.len() {
+        let previous = releases.get(i + 1).and_then(|r| r.date);
+        releases[i].days = releases[i]
+            .date
+            .zip(previous)
+            .map(|(d, p)| (d - p).num_days());
+    }
+    releases.retain(|r| !old_versions.contains(r.version.as_str()));
+
+    // Trim the blank lines around the notes.
+    for release in &mut releases {
+        while release.notes.last().is_some_and(|l| l.trim().is_empty()) {
+            release.notes.pop();
+        }
+        let start = release
+            .notes
+            .iter()
+            .take_while(|l| l.trim().is_empty())
+            .count();
+        release.notes.drain(..start);
+    }
+
+    releases
+}
+
+// The link of every version defined at the end of the changelog, e.g.
+// `[0.15.0]: https://gitlab.com/veloren/veloren/compare/v0.14.0...v0.15.0`, by version. The link of
+// the unreleased changes is under "Unreleased".
+pub fn links(changelog: &str) -> BTreeMap<String, String> {
+    changelog
+        .lines()
+        .filter_map(reference)
+        .map(|(label, url)| (label.trim_start_matches('v').to_string(), url.to_string()))
+        .collect()
+}
+
+// The label and URL of a link reference definition.
+fn reference(line: &str) -> Option<(&str, &str)> {
+    let (label, url) = line.strip_prefix('[')?.split_once("]:")?;
+    let url = url.trim();
+    url.starts_with("http").then_some((label, url))
+}
+
+// The downloads of the version for each configured platform, leaving out those that don't exist
+// (yet). The release is announced either way, so errors only leave out the download.
+async fn downloads(
+    config: &DownloadsConfig,
+    retry_config: &RetryConfig,
+    version: &str,
+) -> Vec<Download> {
+    let mut downloads = vec![];
+    for (name, url) in &config.platforms {
+        let url = url.replace("{version}", version);
+        match http::head(retry_config, &url).await {
+            Ok(_) => downloads.push(Download {
+                name: name.clone(),
+                url,
+            }),
+            Err(e) => tracing::debug!(platform = name, error = %e, "Download is unavailable"),
+        }
+    }
+    downloads
+}
+
+// The version of a section header like `## [0.15.0] - 2023-07-01`, or the variants that other
+// changelogs use, like `## 0.15.0 (2023-07-01)` or `## [v0.15.0]`.
+fn version(line: &str) -> Option<&str> {
+    if diff::is_unreleased(line) {
+        return None;
+    }
+
+    let rest = line.strip_prefix("## ")?.trim_start();
+    let version = match rest.strip_prefix('[') {
+        Some(rest) => rest.split(']').next()?,
+        None => rest.split_whitespace().next()?,
+    };
+    Some(version.trim_start_matches('v')).filter(|v| !v.is_empty())
+}
+
+// The date of a section header, wherever it is written.
+fn date(line: &str) -> Option<NaiveDate> {
+    line.split(|c: char| c.is_whitespace() || "()[]".contains(c))
+        .find_map(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
+}
+
+// Write the changelog the way Keep a Changelog does, which the rest of the parsing expects: with
+// Unix line endings, and entries starting with `- ` rather than the other kinds of bullets.
+fn normalize(changelog: &str) -> String {
+    changelog
+        .lines()
+        .map(
+            |l| match l.strip_prefix("* ").or_else(|| l.strip_prefix("+ ")) {
+                Some(entry) => format!("- {}\n", entry),
+                None => format!("{}\n", l),
+            },
+        )
+        .collect()
+}
+
+// Download the whole changelog at `url`, without looking at the saved one.
+pub async fn fetch(retry_config: &RetryConfig, url: &str) -> reqwest::Result<String> {
+    let changelog = http::get(retry_config, url).await?.text().await?;
+    Ok(normalize(&changelog))
+}
+
+// The commit that the changelog is currently downloaded from.
+pub async fn commit(retry_config: &RetryConfig) -> reqwest::Result<String> {
+    let branch: Branch = http::get_json(retry_config, BRANCH_URL).await?;
+    Ok(branch.commit.id)
+}
+
+// Download the changelog and save it, unless it hasn't changed since it was last saved.
+async fn download(
+    retry_config: &RetryConfig,
+    state: &State,
+    url: &str,
+) -> reqwest::Result<Option<String>> {
+    let Some((md, validators)) =
+        http::get_if_modified(retry_config, state, CHANGELOG_PATH, url).await?
+    else {
+        return Ok(None);
+    };
+    let md = normalize(&md);
+
+    // An incomplete download would be diffed against next time, so it's as good as none.
+    if !complete(&md) {
+        tracing::warn!("Downloaded changelog is incomplete, ignoring it");
+        return Ok(None);
+    }
+
+    state.write(CHANGELOG_PATH, &md);
+    validators.save(state, CHANGELOG_PATH);
+    Ok(Some(md))
+}
+
+fn complete(changelog: &str) -> bool {
+    changelog.lines().any(diff::is_unreleased)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bullets_and_line_endings_are_normalized() {
+        let changelog =
+            "## [Unreleased]\r\n\r\n### Added\r\n\r\n* Boats\r\n+ Lanterns\r\n- Fishing rods";
+        assert_eq!(
+            normalize(changelog),
+            "## [Unreleased]\n\n### Added\n\n- Boats\n- Lanterns\n- Fishing rods\n"
+        );
+    }
+
+    #[test]
+    fn versions_are_parsed_from_headers() {
+        assert_eq!(version("## [0.15.0] - 2023-07-01"), Some("0.15.0"));
+        assert_eq!(version("## 0.15.0 (2023-07-01)"), Some("0.15.0"));
+        assert_eq!(version("## [v0.15.0]"), Some("0.15.0"));
+        assert_eq!(version("## [Unreleased]"), None);
+        assert_eq!(
+            date("## 0.15.0 (2023-07-01)"),
+            NaiveDate::from_ymd_opt(2023, 7, 1)
+        );
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use reqwest::Url;
+use scraper::Html;
+use scraper::Selector;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::RetryConfig;
+use crate::http;
+use crate::sources::Source;
+use crate::sources::Update;
+use crate::state::State;
+
+const DEVBLOGS_PATH: &str = "DEVBLOGS.md";
+const DEVBLOGS_URL: &str = "https://veloren.net/blog/";
+pub const DEVBLOGS_FEED_URL: &str = "https://veloren.net/rss.xml";
+
+// Maximum number of pages (of the feed or the blog index) read when looking for older devblogs.
+const MAX_PAGES: usize = 20;
+
+// Maximum number of devblogs announced by a single poll. More than that are most likely not new,
+// but were missed because the blog changed the links of the posts it had.
+const MAX_NEW: usize = 5;
+
+// Maximum length of the excerpt taken from the first paragraph of a post.
+const EXCERPT_LENGTH: usize = 300;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Devblog {
+    pub url: String,
+    pub title: String,
+    pub date: Option<String>,
+    pub image: Option<String>,
+    pub excerpt: Option<String>,
+}
+
+// A post as found when crawling the blog, and as remembered in the devblogs file, with a line for
+// each of its path, and its date and title if the feed has them, separated by tabs. Files that were
+// written before the dates were kept only have the paths.
+struct Seen {
+    path: String,
+    date: Option<String>,
+    title: Option<String>,
+}
+
+impl Seen {
+    fn parse(line: &str) -> Seen {
+        let mut fields = line.split('\t').map(|s| s.trim().to_string());
+        let mut field = || fields.next().filter(|s| !s.is_empty());
+        Seen {
+            path: field().unwrap_or_default(),
+            date: field(),
+            title: field(),
+        }
+    }
+
+    fn line(&self) -> String {
+        // The title is the last field, so it's only kept from spilling into the next line.
+        let title = self.title.as_deref().unwrap_or_default();
+        let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        let date = self.date.as_deref().unwrap_or_default();
+        format!("{}\t{}\t{}", self.path, date, title)
+    }
+
+    fn published(&self) -> Option<DateTime<chrono::FixedOffset>> {
+        DateTime::parse_from_rfc3339(self.date.as_deref()?).ok()
+    }
+}
+
+// The posts of a blog, found in its feed. Only the Veloren blog is scraped when its feed can't be
+// read.
+pub struct DevblogSource {
+    feed_url: String,
+    retry: RetryConfig,
+}
+
+impl DevblogSource {
+    pub fn new(feed_url: String, retry: RetryConfig) -> DevblogSource {
+        DevblogSource { feed_url, retry }
+    }
+}
+
+#[async_trait]
+impl Source for DevblogSource {
+    fn name(&self) -> &'static str {
+        "devblog"
+    }
+
+    async fn poll(&self, state: &State) -> reqwest::Result<Vec<Update>> {
+        let old: Vec<Seen> = match state.read(DEVBLOGS_PATH) {
+            Ok(s) => s
+                .lines()
+                .map(Seen::parse)
+                .filter(|s| !s.path.is_empty())
+                .collect(),
+            Err(_) => download(&self.retry, state, &self.feed_url, &HashSet::new()).await?,
+        };
+
+        // Only crawl for new devblogs if the feed has changed. If the feed can't be downloaded, the
+        // crawl falls back to scraping the blog anyway.
+        let feed = http::get_if_modified(&self.retry, state, DEVBLOGS_PATH, &self.feed_url).await;
+        if let Ok(None) = feed {
+            return Ok(vec![]);
+        }
+
+        // Extract only the new devblogs. The crawl stops at the first page with any devblog that was
+        // seen, so that a seen one that was taken down since doesn't make it read the whole blog.
+        let seen = old.iter().map(|s| s.path.as_str()).collect::<HashSet<_>>();
+        let devblogs_new = download(&self.retry, state, &self.feed_url, &seen).await?;
+        let mut new = devblogs_new
+            .into_iter()
+            .filter(|s| !seen.contains(s.path.as_str()))
+            .collect::<Vec<_>>();
+        if new.len() > MAX_NEW {
+            tracing::warn!(
+                found = new.len(),
+                announced = MAX_NEW,
+                "Found too many new devblogs, only announcing the newest ones"
+            );
+            new.truncate(MAX_NEW);
+        }
+
+        // The feed lists the newest posts first, while they are announced oldest first, by their
+        // dates if the feed has all of them.
+        new.reverse();
+        if new.iter().all(|s| s.published().is_some()) {
+            new.sort_by_key(|s| s.published());
+        }
+
+        // Fetch the details of each new devblog so they can be posted as embeds. The feed fills in
+        // what the page of the post leaves out.
+        let mut updates = vec![];
+        for s in new {
+            let mut devblog = fetch(&self.retry, &self.feed_url, &s.path).await?;
+            devblog.date = devblog.date.or(s.date);
+            if devblog.title == s.path {
+                devblog.title = s.title.unwrap_or(devblog.title);
+            }
+            updates.push(Update::Devblog(devblog));
+        }
+
+        if let Ok(Some((_, validators))) = feed {
+            validators.save(state, DEVBLOGS_PATH);
+        }
+
+        Ok(updates)
+    }
+}
+
+// Download the list of devblogs and save it. If there are previously seen devblogs, older pages are
+// followed until any of them is found so that posts which fell off the first page are not missed.
+async fn download(
+    retry_config: &RetryConfig,
+    state: &State,
+    feed_url: &str,
+    seen: &HashSet<&str>,
+) -> reqwest::Result<Vec<Seen>> {
+    let devblogs = crawl_from(retry_config, feed_url, seen).await?;
+    let lines = devblogs.iter().map(|s| s.line() + "\n").collect::<String>();
+
+    state.write(DEVBLOGS_PATH, &lines);
+    Ok(devblogs)
+}
+
+// Collect the paths of the devblogs of the blog with the given feed, newest first, until the given
+// devblog is found. If no devblog is given, only the first page is read.
+pub async fn crawl(
+    retry_config: &RetryConfig,
+    feed_url: &str,
+    until: Option<&str>,
+) -> reqwest::Result<Vec<String>> {
+    let until = until.into_iter().collect();
+    let devblogs = crawl_from(retry_config, feed_url, &until).await?;
+    Ok(devblogs.into_iter().map(|s| s.path).collect())
+}
+
+// Like `crawl`, with the dates and titles of the devblogs, until any of the given devblogs is found.
+async fn crawl_from(
+    retry_config: &RetryConfig,
+    feed_url: &str,
+    until: &HashSet<&str>,
+) -> reqwest::Result<Vec<Seen>> {
+    // Prefer the feed since it doesn't depend on the site theme. The scraper is only kept as a
+    // fallback for when the feed is unavailable or fails to parse.
+    let result = crawl_feed(retry_config, feed_url, until).await;
+    if feed_url != DEVBLOGS_FEED_URL {
+        return result;
+    }
+
+    match result {
+        Ok(v) if !v.is_empty() => Ok(v),
+        Ok(_) => crawl_html(retry_config, until).await,
+        Err(e) => {
+            tracing::warn!(error = %e, "Unable to download the devblog feed");
+            crawl_html(retry_config, until).await
+        }
+    }
+}
+
+// Convert a devblog link into the path used to identify it.
+pub fn path(link: &str) -> String {
+    match Url::parse(link) {
+        Ok(u) => u.path().to_string(),
+        Err(_) => link.to_string(),
+    }
+}
+
+// Whether crawling can stop after having collected the given devblogs.
+fn done(devblogs: &[Seen], until: &HashSet<&str>) -> bool {
+    until.is_empty() || devblogs.iter().any(|s| until.contains(s.path.as_str()))
+}
+
+async fn crawl_feed(
+    retry_config: &RetryConfig,
+    feed_url: &str,
+    until: &HashSet<&str>,
+) -> reqwest::Result<Vec<Seen>> {
+    let mut devblogs = vec![];
+    let mut next = Some(feed_url.to_string());
+
+    for _ in 0..MAX_PAGES {
+        let Some(url) = next.take() else {
+            break;
+        };
+
+        let xml = http::get(retry_config, &url).await?.bytes().await?;
+        let feed = match feed_rs::parser::parse(&xml[..]) {
+            Ok(f) => f,
+            Err(e) => {
+                tracing::warn!(error = %e, "Unable to parse the devblog feed");
+                break;
+            }
+        };
+
+        // Only keep the path of each link so that the result matches what the scraper produces.
+        devblogs.extend(feed.entries.iter().filter_map(|e| {
+            Some(Seen {
+                path: path(&e.links.first()?.href),
+                date: e.published.or(e.updated).map(|d| d.to_rfc3339()),
+                title: e.title.as_ref().map(|t| t.content.clone()),
+            })
+        }));
+
+        if done(&devblogs, until) {
+            break;
+        }
+
+        // Feeds that are split over several documents link to the next one (RFC 5005).
+        next = feed
+            .links
+            .iter()
+            .find(|l| l.rel.as_deref() == Some("next"))
+            .map(|l| l.href.clone());
+    }
+
+    Ok(devblogs)
+}
+
+async fn crawl_html(
+    retry_config: &RetryConfig,
+    until: &HashSet<&str>,
+) -> reqwest::Result<Vec<Seen>> {
+    let selector = Selector::parse(".header-link").unwrap();
+    let next_selector = Selector::parse("a[rel='next'], link[rel='next']").unwrap();
+    let link_selector = Selector::parse("a[href]").unwrap();
+
+    let mut devblogs = vec![];
+    let mut next = Some(Url::parse(DEVBLOGS_URL).unwrap());
+
+    for _ in 0..MAX_PAGES {
+        let Some(url) = next.take() else {
+            break;
+        };
+
+        let html = http::get(retry_config, url.as_str()).await?.text().await?;
+        let document = Html::parse_document(&html);
+
+        // Process the html into only the devblog links. The index has no dates, which the page of
+        // each post has instead.
+        devblogs.extend(document.select(&selector).filter_map(|e| {
+            Some(Seen {
+                path: e.value().attr("href")?.to_string(),
+                date: None,
+                title: None,
+            })
+        }));
+
+        if done(&devblogs, until) {
+            break;
+        }
+
+        // Follow the "older posts" link to the next page, if there is one.
+        next = document
+            .select(&next_selector)
+            .chain(document.select(&link_selector).filter(|e| {
+                e.text()
+                    .collect::<String>()
+                    .to_lowercase()
+                    .contains("older")
+            }))
+            .find_map(|e| e.value().attr("href"))
+            .and_then(|s| url.join(s).ok());
+    }
+
+    Ok(devblogs)
+}
+
+// Fetch the page of a single post (given by its path, as stored in the devblogs file) of the blog
+// at `base`, which may be any URL of the blog, and extract the information needed to announce it.
+pub async fn fetch(retry_config: &RetryConfig, base: &str, path: &str) -> reqwest::Result<Devblog> {
+    let url = Url::parse(base)
+        .and_then(|u| u.join(path))
+        .map(|u| u.to_string())
+        .unwrap_or_else(|_| base.to_string() + path);
+
+    let html = http::get(retry_config, &url).await?.text().await?;
+    let document = Html::parse_document(&html);
+
+    let select_attr = |selector: &str, attr: &str| {
+        let selector = Selector::parse(selector).unwrap();
+        document
+            .select(&selector)
+            .find_map(|e| e.value().attr(attr))
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    let select_text = |selector: &str| {
+        let selector = Selector::parse(selector).unwrap();
+        document
+            .select(&selector)
+            .map(|e| e.text().collect::<String>())
+            .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+            .find(|s| !s.is_empty())
+    };
+
+    let title = select_attr("meta[property='og:title']", "content")
+        .or_else(|| select_text("h1"))
+        .or_else(|| select_text("title"))
+        .unwrap_or_else(|| path.to_string());
+
+    let date = select_attr("meta[property='article:published_time']", "content")
+        .or_else(|| select_attr("time", "datetime"))
+        .or_else(|| select_text("time"));
+
+    let image = select_attr("meta[property='og:image']", "content")
+        .and_then(|s| Url::parse(&url).ok()?.join(&s).ok())
+        .map(|u| u.to_string());
+
+    let excerpt = select_text("article p")
+        .or_else(|| select_text("main p"))
+        .or_else(|| select_text("p"))
+        .map(|s| truncate(&s, EXCERPT_LENGTH));
+
+    Ok(Devblog {
+        url,
+        title,
+        date,
+        image,
+        excerpt,
+    })
+}
+
+// Truncate a string to at most `length` characters, cutting at a word boundary if possible.
+fn truncate(s: &str, length: usize) -> String {
+    if s.chars().count() <= length {
+        return s.to_string();
+    }
+
+    let cut = s.char_indices().nth(length).map(|(i, _)| i).unwrap();
+    let cut = s[..cut].rfind(' ').unwrap_or(cut);
+    s[..cut].trim_end().to_string() + "…"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crawling_stops_at_any_seen_devblog() {
+        let devblogs = [Seen::parse("/blog/3"), Seen::parse("/blog/2")];
+        assert!(done(&devblogs, &HashSet::from(["/blog/1", "/blog/2"])));
+        assert!(!done(&devblogs, &HashSet::from(["/blog/1"])));
+        assert!(done(&devblogs, &HashSet::new()));
+    }
+
+    #[test]
+    fn seen_devblogs_are_remembered() {
+        let line = "/blog/devblog-200\t2023-07-01T12:00:00+00:00\tThis Week In Veloren 200";
+        let s = Seen::parse(line);
+        assert_eq!(s.path, "/blog/devblog-200");
+        assert_eq!(s.title.as_deref(), Some("This Week In Veloren 200"));
+        assert!(s.published().is_some());
+        assert_eq!(s.line(), line);
+    }
+
+    #[test]
+    fn old_devblog_files_only_have_paths() {
+        let s = Seen::parse("/blog/devblog-100");
+        assert_eq!(s.path, "/blog/devblog-100");
+        assert!(s.date.is_none() && s.title.is_none());
+        assert_eq!(path("https://veloren.net/blog/devblog-100"), s.path);
+    }
+}
@@ -0,0 +1,124 @@
+use async_trait::async_trait;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::config::GithubConfig;
+use crate::config::RetryConfig;
+use crate::http;
+use crate::sources::unseen;
+use crate::sources::Source;
+use crate::sources::Update;
+use crate::state::State;
+
+const GITHUB_API_URL: &str = "https://api.github.com/repos";
+
+// Number of release notes lines announced per release, after which the link has the rest.
+const MAX_NOTES: usize = 15;
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    name: Option<String>,
+    body: Option<String>,
+    html_url: String,
+    draft: bool,
+    prerelease: bool,
+}
+
+// Announces the new releases of repositories on GitHub, with their release notes.
+pub struct GithubSource {
+    config: GithubConfig,
+    retry: RetryConfig,
+}
+
+impl GithubSource {
+    pub fn new(config: GithubConfig, retry: RetryConfig) -> GithubSource {
+        GithubSource { config, retry }
+    }
+
+    async fn releases(&self, state: &State, repository: &str) -> reqwest::Result<Vec<Update>> {
+        let url = format!("{}/{}/releases?per_page=20", GITHUB_API_URL, repository);
+        let releases: Vec<Release> = http::get_json(&self.retry, &url).await?;
+        let releases = releases
+            .into_iter()
+            .filter(|r| !r.draft && (self.config.prereleases || !r.prerelease))
+            .collect::<Vec<_>>();
+
+        let ids = releases
+            .iter()
+            .map(|r| r.tag_name.clone())
+            .collect::<Vec<_>>();
+        let path = format!("GITHUB-{}.md", repository.replace('/', "_"));
+        let new = unseen(state, &path, &ids);
+
+        // Oldest first, like the entries of the changelog.
+        Ok(releases
+            .iter()
+            .rev()
+            .filter(|r| new.contains(&r.tag_name))
+            .map(|r| {
+                let name = r.name.as_deref().filter(|n| !n.is_empty());
+                let mut entries = vec![format!("- Release notes: <{}>", r.html_url)];
+                entries.extend(notes(repository, r.body.as_deref().unwrap_or_default()));
+
+                Update::Section {
+                    title: format!("{} {}", repository, name.unwrap_or(&r.tag_name)),
+                    entries,
+                }
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Source for GithubSource {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    async fn poll(&self, state: &State) -> reqwest::Result<Vec<Update>> {
+        let mut updates = vec![];
+        for repository in &self.config.repositories {
+            updates.extend(self.releases(state, repository).await?);
+        }
+        Ok(updates)
+    }
+}
+
+// Render GitHub's markdown of the release notes as the bullets of Discord's. Headings are left out,
+// since the notes are a section of the announcement themselves, and so is HTML, which Discord
+// shows as is.
+fn notes(repository: &str, body: &str) -> Vec<String> {
+    let comment = Regex::new(r"(?s)<!--.*?-->").unwrap();
+    let tag = Regex::new(r"</?[A-Za-z][^>]*>").unwrap();
+    let image = Regex::new(r"!\[([^\]]*)\]\(([^)]*)\)").unwrap();
+    let pull = Regex::new(&format!(
+        r"(^|[^(<])(https://github\.com/{}/(?:pull|issues)/(\d+))",
+        regex::escape(repository)
+    ))
+    .unwrap();
+
+    let body = comment.replace_all(body, "");
+    let mut lines = body
+        .lines()
+        .map(|l| {
+            let l = l.trim();
+            let l = l
+                .strip_prefix("* ")
+                .or_else(|| l.strip_prefix("+ "))
+                .or_else(|| l.strip_prefix("- "))
+                .unwrap_or(l);
+            let l = tag.replace_all(l, "");
+            let l = image.replace_all(&l, "[$1]($2)");
+            pull.replace_all(&l, "$1[#$3](<$2>)").trim().to_string()
+        })
+        .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.chars().all(|c| "-*_=".contains(c)))
+        .map(|l| format!("- {}", l))
+        .collect::<Vec<_>>();
+
+    if lines.len() > MAX_NOTES {
+        lines.truncate(MAX_NOTES);
+        lines.push("- …".to_string());
+    }
+    lines
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::MilestonesConfig;
+use crate::config::RetryConfig;
+use crate::http;
+use crate::sources::Source;
+use crate::sources::Update;
+use crate::state::State;
+
+const MILESTONES_PATH: &str = "MILESTONES.json";
+
+#[derive(Deserialize)]
+struct Milestone {
+    id: u64,
+    title: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct IssuesStatistics {
+    statistics: Statistics,
+}
+
+#[derive(Deserialize)]
+struct Statistics {
+    counts: Counts,
+}
+
+#[derive(Deserialize)]
+struct Counts {
+    all: u64,
+    closed: u64,
+}
+
+// How far a milestone has come since it was last announced.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Progress {
+    pub title: String,
+    // The threshold of completion that was reached, in percent, or nothing if the milestone was
+    // closed.
+    pub threshold: Option<u8>,
+}
+
+// What has been announced about an active milestone.
+#[derive(Deserialize, Serialize)]
+struct Seen {
+    title: String,
+    // The highest threshold of completion that has been reached, in percent.
+    threshold: u8,
+}
+
+// Announces when an active milestone of a project on GitLab reaches a threshold of completion, or
+// is closed.
+pub struct MilestoneSource {
+    config: MilestonesConfig,
+    // The GitLab API of the project.
+    api_url: String,
+    retry: RetryConfig,
+}
+
+impl MilestoneSource {
+    pub fn new(config: MilestonesConfig, api_url: String, retry: RetryConfig) -> MilestoneSource {
+        MilestoneSource {
+            config,
+            api_url,
+            retry,
+        }
+    }
+
+    // The highest configured threshold that the share of closed issues of the milestone has
+    // reached, or zero if none has.
+    async fn threshold(&self, milestone: &Milestone) -> reqwest::Result<u8> {
+        let url = format!("{}/issues_statistics", self.api_url);
+        let url = Url::parse_with_params(&url, [("milestone", &milestone.title)])
+            .expect("Unable to build the URL.");
+        let stats: IssuesStatistics = http::get_json(&self.retry, url.as_str()).await?;
+
+        let counts = stats.statistics.counts;
+        if counts.all == 0 {
+            return Ok(0);
+        }
+
+        let percent = counts.closed * 100 / counts.all;
+        Ok(self
+            .config
+            .thresholds
+            .iter()
+            .copied()
+            .filter(|&t| u64::from(t) <= percent)
+            .max()
+            .unwrap_or(0))
+    }
+}
+
+#[async_trait]
+impl Source for MilestoneSource {
+    fn name(&self) -> &'static str {
+        "milestone"
+    }
+
+    // The first poll only remembers the progress of the milestones, like the other sources do with
+    // what they first download.
+    async fn poll(&self, state: &State) -> reqwest::Result<Vec<Update>> {
+        let old: Option<BTreeMap<u64, Seen>> = match state.read(MILESTONES_PATH) {
+            Ok(s) => serde_json::from_str(&s)
+                .inspect_err(|e| tracing::error!(error = %e, "Unable to parse the milestones file"))
+                .ok(),
+            Err(_) => None,
+        };
+
+        let url = format!("{}/milestones?state=active&per_page=100", self.api_url);
+        let active: Vec<Milestone> = http::get_json(&self.retry, &url).await?;
+
+        let mut progress = vec![];
+        let mut completed = vec![];
+        let mut seen = BTreeMap::new();
+        for milestone in active {
+            let threshold = self.threshold(&milestone).await?;
+            let before = old
+                .as_ref()
+                .map(|o| o.get(&milestone.id).map_or(0, |s| s.threshold));
+            if before.is_some_and(|b| threshold > b) {
+                if threshold == 100 {
+                    completed.push(Update::Completed(milestone.title.clone()));
+                }
+                progress.push(Update::Milestone(Progress {
+                    title: milestone.title.clone(),
+                    threshold: Some(threshold),
+                }));
+            }
+
+            seen.insert(
+                milestone.id,
+                Seen {
+                    title: milestone.title,
+                    // Issues may be added later, which must not announce the threshold again.
+                    threshold: threshold.max(before.unwrap_or(0)),
+                },
+            );
+        }
+
+        // Milestones that aren't active anymore have either been closed or deleted.
+        for (id, s) in old.iter().flatten() {
+            if seen.contains_key(id) {
+                continue;
+            }
+
+            let url = format!("{}/milestones/{}", self.api_url, id);
+            match http::get_json::<Milestone>(&self.retry, &url).await {
+                Ok(m) if m.state == "closed" => progress.push(Update::Milestone(Progress {
+                    title: s.title.clone(),
+                    threshold: None,
+                })),
+                Ok(_) => {}
+                Err(e) => tracing::debug!(milestone = id, error = %e, "Milestone is gone"),
+            }
+        }
+
+        state.write(MILESTONES_PATH, &serde_json::to_string(&seen).unwrap());
+
+        Ok(progress.into_iter().chain(completed).collect())
+    }
+}
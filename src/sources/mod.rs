@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::config::DownloadsConfig;
+use crate::config::ProjectConfig;
+use crate::http;
+use crate::state::State;
+
+use artifacts::Artifacts;
+use changelog::Release;
+use devblog::Devblog;
+use milestone::Progress;
+use twitch::Stream;
+
+pub mod artifacts;
+pub mod changelog;
+pub mod devblog;
+mod github;
+pub mod milestone;
+mod reddit;
+pub mod twitch;
+mod wiki;
+mod youtube;
+
+// Something new that a source found since it was last polled.
+#[derive(Deserialize, Serialize)]
+pub enum Update {
+    // New entries of a changelog section, as markdown bullets.
+    Section { title: String, entries: Vec<String> },
+    Release(Release),
+    Artifacts(Artifacts),
+    Devblog(Devblog),
+    Stream(Stream),
+    // Entries that were announced, but have been removed from the changelog since, as markdown
+    // bullets. They aren't announced, but corrected in the announcements that had them.
+    Retracted(Vec<String>),
+    // The progress of a milestone.
+    Milestone(Progress),
+    // A milestone that is now complete, by title. It's announced by the entry of its section, but
+    // may also get a release party.
+    Completed(String),
+}
+
+impl Update {
+    // How many new things this is, counting every entry of a section.
+    pub fn size(&self) -> usize {
+        match self {
+            Update::Section { entries, .. } | Update::Retracted(entries) => entries.len(),
+            Update::Release(_)
+            | Update::Artifacts(_)
+            | Update::Devblog(_)
+            | Update::Stream(_)
+            | Update::Milestone(_) => 1,
+            Update::Completed(_) => 0,
+        }
+    }
+}
+
+// A place where news come from. Each source remembers what it has already seen in the state, so
+// that polling only returns what is actually new.
+#[async_trait]
+pub trait Source: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn poll(&self, state: &State) -> reqwest::Result<Vec<Update>>;
+}
+
+// Number of IDs remembered by `unseen`, which only has to cover what a single poll finds.
+const MAX_SEEN: usize = 200;
+
+// Remember the IDs of what a source found in the state file `name`, newest first, and return the
+// ones that weren't found before. On the first poll nothing is new, so that the whole history isn't
+// announced.
+pub fn unseen(state: &State, name: &str, ids: &[String]) -> Vec<String> {
+    let old = state.read(name).ok();
+    let seen = old.iter().flat_map(|s| s.lines()).collect::<HashSet<_>>();
+
+    let new = ids
+        .iter()
+        .filter(|id| !seen.contains(id.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if new.is_empty() {
+        return vec![];
+    }
+
+    let lines = new
+        .iter()
+        .map(|s| s.as_str())
+        .chain(old.iter().flat_map(|s| s.lines()))
+        .take(MAX_SEEN)
+        .map(|s| s.to_string() + "\n")
+        .collect::<String>();
+    state.write(name, &lines);
+
+    match old {
+        Some(_) => new,
+        None => vec![],
+    }
+}
+
+// The changelog of a project, which is Veloren's own for the preset unless configured otherwise.
+pub fn changelog_url(project: &ProjectConfig) -> Option<String> {
+    project.changelog_url.clone().or_else(|| {
+        project
+            .is_veloren()
+            .then(|| changelog::CHANGELOG_URL.to_string())
+    })
+}
+
+// The feed of the blog of a project, which is the Veloren blog for the preset unless configured
+// otherwise.
+pub fn feed_url(project: &ProjectConfig) -> Option<String> {
+    project.blog_feed_url.clone().or_else(|| {
+        project
+            .is_veloren()
+            .then(|| devblog::DEVBLOGS_FEED_URL.to_string())
+    })
+}
+
+// The base URL of the GitLab API of a project, given by its path.
+pub fn gitlab_api_url(project: &str) -> String {
+    format!(
+        "https://gitlab.com/api/v4/projects/{}",
+        project.replace('/', "%2F")
+    )
+}
+
+// Build the sources of a project: its changelog, blog and GitLab releases, and for Veloren those
+// enabled by the config. Offline, those that need to log in are left out, as their responses aren't
+// cached.
+pub fn from_config(config: &Config, project: &ProjectConfig) -> Vec<Box<dyn Source>> {
+    let veloren = project.is_veloren();
+    let changelog_url = changelog_url(project);
+    let feed_url = feed_url(project);
+
+    let mut sources: Vec<Box<dyn Source>> = vec![];
+    if let Some(url) = changelog_url {
+        // Only Veloren's releases have known downloads.
+        let downloads = match veloren {
+            true => config.downloads.clone(),
+            false => DownloadsConfig {
+                launcher: None,
+                platforms: Default::default(),
+            },
+        };
+        sources.push(Box::new(changelog::ChangelogSource::new(
+            url,
+            downloads,
+            config.retry.clone(),
+        )));
+    }
+    if let Some(url) = feed_url {
+        sources.push(Box::new(devblog::DevblogSource::new(
+            url,
+            config.retry.clone(),
+        )));
+    }
+    let api_url = project.gitlab_project().map(gitlab_api_url);
+    if let Some(url) = &api_url {
+        sources.push(Box::new(artifacts::ArtifactsSource::new(
+            url.clone(),
+            config.retry.clone(),
+        )));
+    }
+    if !veloren {
+        return sources;
+    }
+
+    if let Some((c, url)) = config.milestones.as_ref().zip(api_url) {
+        sources.push(Box::new(milestone::MilestoneSource::new(
+            c.clone(),
+            url,
+            config.retry.clone(),
+        )));
+    }
+
+    if let Some(c) = config.reddit.as_ref().filter(|_| online("reddit")) {
+        sources.push(Box::new(reddit::RedditSource::new(
+            c.clone(),
+            config.retry.clone(),
+        )));
+    }
+
+    if let Some(c) = &config.youtube {
+        sources.push(Box::new(youtube::YoutubeSource::new(
+            c.clone(),
+            config.retry.clone(),
+        )));
+    }
+
+    if let Some(c) = &config.github {
+        sources.push(Box::new(github::GithubSource::new(
+            c.clone(),
+            config.retry.clone(),
+        )));
+    }
+
+    if let Some(c) = config.twitch.as_ref().filter(|_| online("twitch")) {
+        sources.push(Box::new(twitch::TwitchSource::new(
+            c.clone(),
+            config.retry.clone(),
+        )));
+    }
+
+    if let Some(c) = &config.wiki {
+        sources.push(Box::new(wiki::WikiSource::new(
+            c.clone(),
+            config.retry.clone(),
+        )));
+    }
+
+    sources
+}
+
+fn online(source: &str) -> bool {
+    if http::offline() {
+        tracing::warn!(source, "Not polling the source in offline mode");
+    }
+    !http::offline()
+}
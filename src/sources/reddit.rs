@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::config::RedditConfig;
+use crate::config::RetryConfig;
+use crate::http;
+use crate::retry::retry;
+use crate::sources::unseen;
+use crate::sources::Source;
+use crate::sources::Update;
+use crate::state::State;
+
+const REDDIT_PATH: &str = "REDDIT.md";
+const REDDIT_URL: &str = "https://www.reddit.com";
+
+// Reddit rejects requests without a descriptive user agent.
+
+#[derive(Deserialize)]
+struct Listing {
+    data: ListingData,
+}
+
+#[derive(Deserialize)]
+struct ListingData {
+    children: Vec<Child>,
+}
+
+#[derive(Deserialize)]
+struct Child {
+    data: Post,
+}
+
+#[derive(Deserialize)]
+struct Post {
+    id: String,
+    title: String,
+    permalink: String,
+    link_flair_text: Option<String>,
+}
+
+// Includes the posts of the subreddit with one of the configured flairs.
+pub struct RedditSource {
+    config: RedditConfig,
+    retry: RetryConfig,
+}
+
+impl RedditSource {
+    pub fn new(config: RedditConfig, retry: RetryConfig) -> RedditSource {
+        RedditSource { config, retry }
+    }
+
+    fn flaired(&self, post: &Post) -> bool {
+        let Some(flair) = &post.link_flair_text else {
+            return false;
+        };
+        self.config
+            .flairs
+            .iter()
+            .any(|f| f.eq_ignore_ascii_case(flair.trim()))
+    }
+}
+
+#[async_trait]
+impl Source for RedditSource {
+    fn name(&self) -> &'static str {
+        "reddit"
+    }
+
+    async fn poll(&self, state: &State) -> reqwest::Result<Vec<Update>> {
+        let url = format!(
+            "{}/r/{}/new.json?limit=100",
+            REDDIT_URL, self.config.subreddit
+        );
+        let client = http::client();
+        let listing = retry(&self.retry, || async {
+            client
+                .get(&url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Listing>()
+                .await
+        })
+        .await?;
+
+        let posts = listing
+            .data
+            .children
+            .into_iter()
+            .map(|c| c.data)
+            .collect::<Vec<_>>();
+        let ids = posts.iter().map(|p| p.id.clone()).collect::<Vec<_>>();
+        let new = unseen(state, REDDIT_PATH, &ids);
+
+        // Oldest first, like the entries of the changelog.
+        let entries = posts
+            .into_iter()
+            .rev()
+            .filter(|p| new.contains(&p.id) && self.flaired(p))
+            .map(|p| format!("- [{}]({}{})", p.title, REDDIT_URL, p.permalink))
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(vec![Update::Section {
+            title: "Reddit".to_string(),
+            entries,
+        }])
+    }
+}
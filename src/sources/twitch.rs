@@ -0,0 +1,132 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::RetryConfig;
+use crate::config::TwitchConfig;
+use crate::http;
+use crate::retry::retry;
+use crate::sources::unseen;
+use crate::sources::Source;
+use crate::sources::Update;
+use crate::state::State;
+
+const TWITCH_PATH: &str = "TWITCH.md";
+const TOKEN_URL: &str = "https://id.twitch.tv/oauth2/token";
+const STREAMS_URL: &str = "https://api.twitch.tv/helix/streams";
+
+// A channel that went live.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Stream {
+    pub channel: String,
+    pub title: String,
+    pub game: String,
+    pub url: String,
+    pub thumbnail: String,
+}
+
+#[derive(Deserialize)]
+struct Token {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct Streams {
+    data: Vec<StreamData>,
+}
+
+#[derive(Deserialize)]
+struct StreamData {
+    id: String,
+    user_login: String,
+    user_name: String,
+    title: String,
+    game_name: String,
+    thumbnail_url: String,
+}
+
+// Announces when one of the configured Twitch channels goes live, once per stream.
+pub struct TwitchSource {
+    config: TwitchConfig,
+    retry: RetryConfig,
+}
+
+impl TwitchSource {
+    pub fn new(config: TwitchConfig, retry: RetryConfig) -> TwitchSource {
+        TwitchSource { config, retry }
+    }
+}
+
+#[async_trait]
+impl Source for TwitchSource {
+    fn name(&self) -> &'static str {
+        "twitch"
+    }
+
+    async fn poll(&self, state: &State) -> reqwest::Result<Vec<Update>> {
+        let client = http::client();
+
+        // App access tokens last for about two months, but getting a new one every poll is simpler
+        // than keeping track of when it expires.
+        let token = retry(&self.retry, || async {
+            client
+                .post(TOKEN_URL)
+                .query(&[
+                    ("client_id", self.config.client_id.as_str()),
+                    ("client_secret", self.config.client_secret.as_str()),
+                    ("grant_type", "client_credentials"),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Token>()
+                .await
+        })
+        .await?;
+
+        let logins = self
+            .config
+            .channels
+            .iter()
+            .map(|c| ("user_login", c.as_str()))
+            .collect::<Vec<_>>();
+        let streams = retry(&self.retry, || async {
+            client
+                .get(STREAMS_URL)
+                .query(&logins)
+                .header("Client-Id", &self.config.client_id)
+                .bearer_auth(&token.access_token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Streams>()
+                .await
+        })
+        .await?;
+
+        let ids = streams
+            .data
+            .iter()
+            .map(|s| s.id.clone())
+            .collect::<Vec<_>>();
+        let new = unseen(state, TWITCH_PATH, &ids);
+
+        Ok(streams
+            .data
+            .into_iter()
+            .filter(|s| new.contains(&s.id))
+            .map(|s| {
+                Update::Stream(Stream {
+                    url: format!("https://www.twitch.tv/{}", s.user_login),
+                    channel: s.user_name,
+                    title: s.title,
+                    game: s.game_name,
+                    thumbnail: s
+                        .thumbnail_url
+                        .replace("{width}", "1280")
+                        .replace("{height}", "720"),
+                })
+            })
+            .collect())
+    }
+}
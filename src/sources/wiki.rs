@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::config::RetryConfig;
+use crate::config::WikiConfig;
+use crate::http;
+use crate::sources::Source;
+use crate::sources::Update;
+use crate::state::State;
+
+// The ID of the latest change that has been seen.
+const WIKI_PATH: &str = "WIKI.md";
+
+#[derive(Deserialize)]
+struct Response {
+    query: Query,
+}
+
+#[derive(Deserialize)]
+struct Query {
+    recentchanges: Vec<Change>,
+}
+
+#[derive(Deserialize)]
+struct Change {
+    #[serde(rename = "type")]
+    kind: String,
+    title: String,
+    rcid: u64,
+    #[serde(default)]
+    oldlen: i64,
+    #[serde(default)]
+    newlen: i64,
+}
+
+// Summarizes the notable changes to the pages of a MediaWiki wiki: new pages, and pages whose size
+// changed by a lot.
+pub struct WikiSource {
+    config: WikiConfig,
+    retry: RetryConfig,
+}
+
+impl WikiSource {
+    pub fn new(config: WikiConfig, retry: RetryConfig) -> WikiSource {
+        WikiSource { config, retry }
+    }
+}
+
+#[async_trait]
+impl Source for WikiSource {
+    fn name(&self) -> &'static str {
+        "wiki"
+    }
+
+    async fn poll(&self, state: &State) -> reqwest::Result<Vec<Update>> {
+        let last_seen = state
+            .read(WIKI_PATH)
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok());
+
+        let url = Url::parse_with_params(
+            &self.config.api_url,
+            [
+                ("action", "query"),
+                ("list", "recentchanges"),
+                ("rcprop", "title|ids|sizes"),
+                ("rctype", "new|edit"),
+                ("rcshow", "!bot"),
+                ("rclimit", "500"),
+                ("format", "json"),
+            ],
+        )
+        .expect("Unable to parse the wiki API URL.");
+        let response: Response = http::get_json(&self.retry, url.as_str()).await?;
+
+        // The changes are listed newest first.
+        let changes = response.query.recentchanges;
+        let Some(latest) = changes.first().map(|c| c.rcid) else {
+            return Ok(vec![]);
+        };
+        state.write(WIKI_PATH, &latest.to_string());
+
+        // On the first poll, the changes are only remembered.
+        let Some(last_seen) = last_seen else {
+            return Ok(vec![]);
+        };
+
+        // Whether each page is new, and by how much its size changed in total.
+        let mut pages: BTreeMap<&str, (bool, i64)> = BTreeMap::new();
+        for change in changes.iter().filter(|c| c.rcid > last_seen) {
+            let page = pages.entry(&change.title).or_default();
+            page.0 |= change.kind == "new";
+            page.1 += change.newlen - change.oldlen;
+        }
+
+        let entries = pages
+            .into_iter()
+            .filter_map(|(title, (new, size))| match new {
+                true => Some(format!("- New page: {}", title)),
+                false if size.unsigned_abs() >= self.config.min_bytes => {
+                    Some(format!("- {} ({:+} bytes)", title, size))
+                }
+                false => None,
+            })
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(vec![Update::Section {
+            title: "Wiki".to_string(),
+            entries,
+        }])
+    }
+}
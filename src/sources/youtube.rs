@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+
+use crate::config::RetryConfig;
+use crate::config::YoutubeConfig;
+use crate::http;
+use crate::sources::unseen;
+use crate::sources::Source;
+use crate::sources::Update;
+use crate::state::State;
+
+const YOUTUBE_PATH: &str = "YOUTUBE.md";
+const YOUTUBE_FEED_URL: &str = "https://www.youtube.com/feeds/videos.xml";
+
+// Announces the new uploads of a YouTube channel.
+pub struct YoutubeSource {
+    config: YoutubeConfig,
+    retry: RetryConfig,
+}
+
+impl YoutubeSource {
+    pub fn new(config: YoutubeConfig, retry: RetryConfig) -> YoutubeSource {
+        YoutubeSource { config, retry }
+    }
+}
+
+#[async_trait]
+impl Source for YoutubeSource {
+    fn name(&self) -> &'static str {
+        "youtube"
+    }
+
+    // The videos are listed by their links, which Discord shows as embeds that play the video.
+    async fn poll(&self, state: &State) -> reqwest::Result<Vec<Update>> {
+        let url = format!("{}?channel_id={}", YOUTUBE_FEED_URL, self.config.channel_id);
+        let xml = http::get(&self.retry, &url).await?.bytes().await?;
+        let feed = match feed_rs::parser::parse(&xml[..]) {
+            Ok(f) => f,
+            Err(e) => {
+                tracing::warn!(error = %e, "Unable to parse the YouTube feed");
+                return Ok(vec![]);
+            }
+        };
+
+        let ids = feed
+            .entries
+            .iter()
+            .map(|e| e.id.clone())
+            .collect::<Vec<_>>();
+        let new = unseen(state, YOUTUBE_PATH, &ids);
+
+        // Oldest first, like the entries of the changelog.
+        let entries = feed
+            .entries
+            .iter()
+            .rev()
+            .filter(|e| new.contains(&e.id))
+            .filter_map(|e| {
+                let title = e.title.as_ref().map_or("New video", |t| t.content.as_str());
+                let link = e.links.first()?;
+                Some(format!("- {}: {}", title, link.href))
+            })
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(vec![Update::Section {
+            title: "Videos".to_string(),
+            entries,
+        }])
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use directories::ProjectDirs;
+use serde::de::DeserializeOwned;
+
+const LOCK_PATH: &str = "veloren-changelog.lock";
+
+// The files that older versions kept in the working directory, which are moved to the state
+// directory along with their validators.
+const LEGACY_FILES: &[&str] = &[
+    "CHANGELOG.md",
+    "DEVBLOGS.md",
+    "ARTIFACTS.md",
+    "MILESTONES.json",
+    "WIKI.md",
+    "TWITCH.md",
+    "YOUTUBE.md",
+    "REDDIT.md",
+    "PENDING.json",
+    "guilds.json",
+    "ANNOUNCEMENTS.xml",
+];
+
+// The first line of every state file, followed by the checksum of the rest of the file. Files
+// without it were written before it existed, and are read as they are.
+const HEADER: &str = "veloren-changelog state v";
+const VERSION: u32 = 1;
+
+// The files remembering what has already been announced. Each source keeps its own files.
+//
+// Writes are only staged, and are saved by `commit` once the announcement has been delivered, so
+// that the changes are announced again on the next run if anything goes wrong before that.
+pub struct State {
+    dir: PathBuf,
+    // The new contents of each file, or nothing for files that are deleted.
+    staged: Mutex<BTreeMap<String, Option<String>>>,
+}
+
+impl State {
+    pub fn new(dir: impl Into<PathBuf>) -> State {
+        State {
+            dir: dir.into(),
+            staged: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    // Take the lock that prevents several instances from running at once, for as long as the
+    // returned file is kept open. Returns nothing if another instance holds the lock.
+    pub fn lock(&self) -> Option<fs::File> {
+        let file = fs::File::create(self.dir.join(LOCK_PATH)).expect("Unable to write to file.");
+        match file.try_lock() {
+            Ok(()) => Some(file),
+            Err(fs::TryLockError::WouldBlock) => None,
+            Err(fs::TryLockError::Error(e)) => panic!("Unable to lock {}: {}", LOCK_PATH, e),
+        }
+    }
+
+    // Read a file, including any staged but not yet committed changes. A file that was damaged,
+    // e.g. by running out of disk space, can't be read, which sources treat like a missing file:
+    // they start over from what they find now, without announcing anything.
+    pub fn read(&self, name: &str) -> io::Result<String> {
+        match self.staged.lock().unwrap().get(name) {
+            Some(Some(s)) => return Ok(s.clone()),
+            Some(None) => return Err(io::ErrorKind::NotFound.into()),
+            None => {}
+        }
+
+        let result = fs::read_to_string(self.dir.join(name)).and_then(verify);
+        match &result {
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                tracing::error!(file = name, error = %e, "State file is damaged, starting over");
+            }
+            _ => {}
+        }
+        result
+    }
+
+    pub fn write(&self, name: &str, contents: &str) {
+        self.staged
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), Some(contents.to_string()));
+    }
+
+    // Delete a file whose contents turned out to be unusable, so that it is read as missing.
+    pub fn forget(&self, name: &str) {
+        self.staged.lock().unwrap().insert(name.to_string(), None);
+    }
+
+    // The staged changes, which are kept aside while their announcement waits for a review.
+    pub fn staged(&self) -> BTreeMap<String, Option<String>> {
+        self.staged.lock().unwrap().clone()
+    }
+
+    // Stage changes that were kept aside by `staged`.
+    pub fn restore(&self, staged: BTreeMap<String, Option<String>>) {
+        self.staged.lock().unwrap().extend(staged);
+    }
+
+    // Save every staged file.
+    pub fn commit(&self) {
+        let staged = std::mem::take(&mut *self.staged.lock().unwrap());
+        for (name, contents) in staged {
+            let path = self.dir.join(name);
+            match contents {
+                Some(s) => write_atomic(&path, seal(&s)),
+                None => {
+                    fs::remove_file(path).ok();
+                }
+            }
+        }
+    }
+}
+
+// Read a file that is saved right away instead of being staged, checking its header like `read`.
+pub fn read_sealed(path: &Path) -> io::Result<String> {
+    fs::read_to_string(path).and_then(verify)
+}
+
+// Save a file right away, with the header that `read_sealed` checks.
+pub fn write_sealed(path: &Path, contents: &str) {
+    write_atomic(path, seal(contents));
+}
+
+// Read and parse a file that is saved right away, starting over if it is damaged. The damaged file
+// is kept next to it, so that it isn't overwritten by the next change and can still be repaired by
+// hand.
+pub fn load_sealed<T: Default + DeserializeOwned>(path: &Path) -> T {
+    let result = read_sealed(path).and_then(|s| {
+        serde_json::from_str(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    });
+    match result {
+        Ok(value) => value,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => T::default(),
+        Err(e) if e.kind() != io::ErrorKind::InvalidData => {
+            tracing::error!(file = %path.display(), error = %e, "Unable to read the state file");
+            T::default()
+        }
+        Err(e) => {
+            let mut damaged = path.as_os_str().to_owned();
+            damaged.push(".damaged");
+            tracing::error!(
+                file = %path.display(),
+                error = %e,
+                moved_to = ?damaged,
+                "State file is damaged, starting over"
+            );
+            if let Err(e) = fs::rename(path, &damaged) {
+                tracing::error!(file = %path.display(), error = %e, "Unable to move the damaged file");
+            }
+            T::default()
+        }
+    }
+}
+
+// Prepend the header to the contents of a file.
+fn seal(contents: &str) -> String {
+    format!(
+        "{}{} {:016x}\n{}",
+        HEADER,
+        VERSION,
+        checksum(contents),
+        contents
+    )
+}
+
+// The contents of a file after the header, if they match the checksum in the header.
+fn verify(file: String) -> io::Result<String> {
+    let Some(rest) = file.strip_prefix(HEADER) else {
+        return Ok(file);
+    };
+
+    let invalid = |e: &str| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let (header, contents) = rest
+        .split_once('\n')
+        .ok_or_else(|| invalid("truncated header"))?;
+    let (version, sum) = header
+        .split_once(' ')
+        .ok_or_else(|| invalid("truncated header"))?;
+    if version.parse::<u32>().ok() != Some(VERSION) {
+        return Err(invalid(&format!(
+            "written by another version ({})",
+            version
+        )));
+    }
+    if u64::from_str_radix(sum, 16).ok() != Some(checksum(contents)) {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    Ok(contents.to_string())
+}
+
+// The 64-bit FNV-1a hash, which is stable across builds unlike the hasher of the standard library.
+pub fn checksum(contents: &str) -> u64 {
+    contents.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// The directory of the state files: the one systemd creates for `StateDirectory=`, otherwise
+// `$XDG_STATE_HOME/veloren-changelog`. The working directory is only used if neither is known.
+pub fn default_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("STATE_DIRECTORY") {
+        return systemd_dir(dir);
+    }
+    project_dirs()
+        .and_then(|d| d.state_dir().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+// The directory of the cached responses, like the state directory.
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("CACHE_DIRECTORY") {
+        return systemd_dir(dir);
+    }
+    project_dirs()
+        .map(|d| d.cache_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("cache"))
+}
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "veloren-changelog")
+}
+
+// systemd separates the directories with colons if several are configured.
+fn systemd_dir(dirs: std::ffi::OsString) -> PathBuf {
+    let dirs = dirs.to_string_lossy().into_owned();
+    PathBuf::from(dirs.split(':').next().unwrap_or_default())
+}
+
+// Create the state directory, and move the files that older versions kept in the working
+// directory into it, unless it already has its own.
+pub fn prepare(dir: &Path) {
+    fs::create_dir_all(dir).expect("Unable to create the state directory.");
+    if fs::canonicalize(dir).ok() == fs::canonicalize(".").ok() {
+        return;
+    }
+
+    let names = LEGACY_FILES
+        .iter()
+        .flat_map(|name| [name.to_string(), format!("{}.validators", name)]);
+    for name in names {
+        let (old, new) = (Path::new(&name), dir.join(&name));
+        if !old.exists() || new.exists() {
+            continue;
+        }
+
+        // Renaming doesn't work across file systems, where the file is copied instead.
+        let moved =
+            fs::rename(old, &new).or_else(|_| fs::copy(old, &new).and(fs::remove_file(old)));
+        match moved {
+            Ok(()) => tracing::info!(file = name, to = %dir.display(), "Moved the state file"),
+            Err(e) => tracing::error!(file = name, error = %e, "Unable to move the state file"),
+        }
+    }
+}
+
+// Write to a temporary file first and then rename it over the old file, so that the file is never
+// left half-written.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) {
+    try_write_atomic(path, contents).expect("Unable to write to file.");
+}
+
+// Like `write_atomic`, but returning the error, for files that are allowed to fail to be written.
+pub fn try_write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+// An empty directory for the files of a test. The process ID keeps the runs of different checkouts
+// apart.
+#[cfg(test)]
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("veloren-changelog-{}-{}", name, std::process::id()));
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_contents_are_verified() {
+        let contents = "## [Unreleased]\n- Boats\n";
+        assert_eq!(verify(seal(contents)).unwrap(), contents);
+    }
+
+    #[test]
+    fn damaged_contents_are_rejected() {
+        let sealed = seal("## [Unreleased]\n- Boats\n");
+        let truncated = sealed[..sealed.len() - 3].to_string();
+        let error = verify(truncated).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let other = sealed.replacen(&format!("v{}", VERSION), "v0", 1);
+        assert_eq!(
+            verify(other).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn unsealed_contents_are_read_as_they_are() {
+        assert_eq!(verify("- Boats\n".to_string()).unwrap(), "- Boats\n");
+    }
+
+    #[test]
+    fn staged_changes_are_only_saved_by_commit() {
+        let dir = test_dir("state-commit");
+
+        let state = State::new(&dir);
+        state.write("CHANGELOG.md", "- Boats\n");
+        assert_eq!(state.read("CHANGELOG.md").unwrap(), "- Boats\n");
+        assert!(State::new(&dir).read("CHANGELOG.md").is_err());
+
+        state.commit();
+        assert_eq!(State::new(&dir).read("CHANGELOG.md").unwrap(), "- Boats\n");
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::announcement::Announcement;
+use crate::config::SummaryConfig;
+use crate::http;
+
+// Generating a summary takes a while, but an announcement shouldn't wait for long on it.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+const PROMPT: &str = "You write the news for the players of Veloren, an open-source voxel RPG. \
+Summarize the following changes in two or three plain-language sentences. Answer with the summary \
+only.";
+
+#[derive(Deserialize)]
+struct Completion {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: Message,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    content: String,
+}
+
+// Ask the model of an OpenAI-compatible API to summarize the changes of the announcement. The
+// summary is optional, so nothing is returned if the API can't be reached or answers nonsense.
+pub async fn summarize(config: &SummaryConfig, announcement: &Announcement) -> Option<String> {
+    if announcement.changes.is_empty() {
+        return None;
+    }
+
+    let url = config.url.trim_end_matches('/').to_string() + "/chat/completions";
+    let mut request = http::client().post(&url).timeout(TIMEOUT).json(&json!({
+        "model": config.model,
+        "messages": [
+            { "role": "system", "content": PROMPT },
+            { "role": "user", "content": announcement.changes.join("\n") },
+        ],
+    }));
+    if let Some(key) = &config.api_key {
+        request = request.bearer_auth(key);
+    }
+
+    let result = async {
+        request
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Completion>()
+            .await
+    }
+    .await;
+
+    match result {
+        Ok(c) => {
+            let summary = c.choices.into_iter().next()?.message.content;
+            Some(summary.trim().to_string()).filter(|s| !s.is_empty())
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "Unable to summarize the announcement");
+            None
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::config::TranslationBackend;
+use crate::config::TranslationConfig;
+use crate::http;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+struct DeeplResponse {
+    translations: Vec<DeeplTranslation>,
+}
+
+#[derive(Deserialize)]
+struct DeeplTranslation {
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibretranslateResponse {
+    translated_text: Vec<String>,
+}
+
+// Translate the English texts into the language with the given code, in the same order. Nothing is
+// returned if the service can't be reached, so that the texts are posted untranslated instead.
+pub async fn translate(
+    config: &TranslationConfig,
+    texts: &[&str],
+    language: &str,
+) -> Option<Vec<String>> {
+    if texts.is_empty() {
+        return Some(vec![]);
+    }
+
+    let client = http::client();
+    let url = config.url.trim_end_matches('/');
+
+    let result = match config.backend {
+        TranslationBackend::Deepl => {
+            let mut request = client.post(url.to_string() + "/v2/translate").json(&json!({
+                "text": texts,
+                "source_lang": "EN",
+                "target_lang": language.to_uppercase(),
+            }));
+            if let Some(key) = &config.api_key {
+                request = request.header("Authorization", format!("DeepL-Auth-Key {}", key));
+            }
+
+            send::<DeeplResponse>(request)
+                .await
+                .map(|r| r.translations.into_iter().map(|t| t.text).collect())
+        }
+        TranslationBackend::Libretranslate => {
+            let request = client.post(url.to_string() + "/translate").json(&json!({
+                "q": texts,
+                "source": "en",
+                "target": language,
+                "format": "text",
+                "api_key": config.api_key,
+            }));
+
+            send::<LibretranslateResponse>(request)
+                .await
+                .map(|r| r.translated_text)
+        }
+    };
+
+    match result {
+        Ok(translations) if translations.len() == texts.len() => Some(translations),
+        Ok(_) => {
+            tracing::warn!(language, "Translation service left out some of the entries");
+            None
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, language, "Unable to translate the announcement");
+            None
+        }
+    }
+}
+
+async fn send<T: serde::de::DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+) -> reqwest::Result<T> {
+    request
+        .timeout(TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<T>()
+        .await
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/replay");
+
+// A copy of the state of the fixture, which the run changes. The process ID keeps the runs of
+// different checkouts apart.
+fn state(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("veloren-changelog-{}-{}", name, std::process::id()));
+    fs::remove_dir_all(&dir).ok();
+    copy(&Path::new(FIXTURE).join("state"), &dir.join("state"));
+    dir
+}
+
+fn copy(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let path = to.join(entry.file_name());
+        match entry.file_type().unwrap().is_dir() {
+            true => copy(&entry.path(), &path),
+            false => {
+                fs::copy(entry.path(), path).unwrap();
+            }
+        }
+    }
+}
+
+// Replay the recorded responses against the state in `dir`, returning the exit status.
+fn replay(dir: &Path) -> Option<i32> {
+    Command::new(env!("CARGO_BIN_EXE_veloren-changelog"))
+        .args(["--config", &format!("{}/config.toml", FIXTURE)])
+        .args(["--replay", &format!("{}/responses", FIXTURE)])
+        .arg("--capture")
+        .arg(dir.join("capture.txt"))
+        .arg("--state-dir")
+        .arg(dir.join("state"))
+        .arg("--quiet")
+        .env_remove("DISCORD_TOKEN")
+        .status()
+        .unwrap()
+        .code()
+}
+
+#[test]
+fn replayed_run_is_captured() {
+    let dir = state("replay-captured");
+    assert_eq!(replay(&dir), Some(0));
+
+    let captured = fs::read_to_string(dir.join("capture.txt")).unwrap();
+    let expected = fs::read_to_string(Path::new(FIXTURE).join("expected.txt")).unwrap();
+    assert_eq!(captured, expected);
+}
+
+#[test]
+fn replayed_run_is_only_announced_once() {
+    let dir = state("replay-once");
+    assert_eq!(replay(&dir), Some(0));
+    assert_eq!(replay(&dir), Some(4));
+
+    let captured = fs::read_to_string(dir.join("capture.txt")).unwrap();
+    let expected = fs::read_to_string(Path::new(FIXTURE).join("expected.txt")).unwrap();
+    assert_eq!(captured, expected);
+}